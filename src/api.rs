@@ -0,0 +1,280 @@
+use hyper::body::Buf;
+use hyper::header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE, USER_AGENT};
+use hyper::{Method, Request, Uri};
+use selectel_mks::error::Error;
+use selectel_mks::nodegroup;
+use selectel_mks::resource_url::{API_VERSION, CLUSTERS, NODEGROUPS};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::timeout;
+
+use crate::nodegroup::Taint;
+
+// Request timeout, the same as the default one of selectel_mks::Client.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// Environment variables from Cargo.
+static PKG_NAME: &str = env!("CARGO_PKG_NAME");
+static PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+type HttpsConnector = hyper_rustls::HttpsConnector<hyper::client::HttpConnector>;
+
+/// Cluster update options, parameters that are not provided are left as is.
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct UpdateClusterOpts {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) maintenance_window_start: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) enable_autorepair: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) enable_patch_version_auto_upgrade: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+struct UpdateClusterOptsRoot<'a> {
+    cluster: &'a UpdateClusterOpts,
+}
+
+/// Nodegroup create options with taints and autoscaling that `nodegroup::schemas::CreateOpts`
+/// doesn't have.
+#[derive(Debug, Serialize)]
+pub(crate) struct CreateNodegroupOpts<'a> {
+    #[serde(flatten)]
+    pub(crate) opts: &'a nodegroup::schemas::CreateOpts,
+
+    #[serde(skip_serializing_if = "<[Taint]>::is_empty")]
+    pub(crate) taints: &'a [Taint],
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) enable_autoscale: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) autoscale_min_nodes: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) autoscale_max_nodes: Option<u32>,
+}
+
+/// Nodegroup update options, parameters that are not provided are left as is.
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct UpdateNodegroupOpts {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) labels: Option<HashMap<String, String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) taints: Option<Vec<Taint>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) enable_autoscale: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) autoscale_min_nodes: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) autoscale_max_nodes: Option<u32>,
+}
+
+/// Nodegroup fields that `nodegroup::schemas::Nodegroup` doesn't have.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct NodegroupDetails {
+    #[serde(default)]
+    pub(crate) taints: Vec<Taint>,
+
+    #[serde(default)]
+    pub(crate) enable_autoscale: bool,
+
+    pub(crate) autoscale_min_nodes: Option<u32>,
+    pub(crate) autoscale_max_nodes: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) keypair_name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) affinity_policy: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) cpus: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) ram_mb: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct NodegroupRoot<T> {
+    nodegroup: T,
+}
+
+/// Client for MKS API methods that are not available in `selectel_mks::Client`.
+pub(crate) struct Client {
+    client: hyper::Client<HttpsConnector>,
+    token: String,
+    base_endpoint: Uri,
+}
+
+impl Client {
+    pub(crate) fn new(base_endpoint: &str, token: &str) -> Result<Client, Error> {
+        if token.is_empty() {
+            return Err(Error::EmptyTokenError);
+        }
+
+        let base_endpoint = base_endpoint
+            .parse::<Uri>()
+            .map_err(|_| Error::EndpointError)?;
+        if base_endpoint.scheme().is_none() || base_endpoint.authority().is_none() {
+            return Err(Error::EndpointError);
+        }
+
+        Ok(Client {
+            client: hyper::Client::builder().build(HttpsConnector::new()),
+            token: String::from(token),
+            base_endpoint,
+        })
+    }
+
+    /// Get a cluster kubeconfig.
+    pub(crate) fn get_kubeconfig(&self, cluster_id: &str) -> Result<String, Error> {
+        let path = format!("/{}/{}/{}/kubeconfig", API_VERSION, CLUSTERS, cluster_id);
+        let req = self.new_request(Method::GET, &path, None)?;
+
+        self.do_request(req)
+    }
+
+    /// Update cluster parameters.
+    pub(crate) fn update_cluster(
+        &self,
+        cluster_id: &str,
+        opts: &UpdateClusterOpts,
+    ) -> Result<(), Error> {
+        let path = format!("/{}/{}/{}", API_VERSION, CLUSTERS, cluster_id);
+        let body = serde_json::to_string(&UpdateClusterOptsRoot { cluster: opts })
+            .map_err(Error::SerializeError)?;
+        let req = self.new_request(Method::PUT, &path, Some(body))?;
+
+        self.do_request(req).map(|_| ())
+    }
+
+    /// Get nodegroup fields that are not available in `selectel_mks::Client::get_nodegroup`.
+    pub(crate) fn get_nodegroup_details(
+        &self,
+        cluster_id: &str,
+        nodegroup_id: &str,
+    ) -> Result<NodegroupDetails, Error> {
+        let path = format!(
+            "/{}/{}/{}/{}/{}",
+            API_VERSION, CLUSTERS, cluster_id, NODEGROUPS, nodegroup_id
+        );
+        let req = self.new_request(Method::GET, &path, None)?;
+        let body = self.do_request(req)?;
+
+        let deserialized: NodegroupRoot<NodegroupDetails> =
+            serde_json::from_str(&body).map_err(|err| Error::DeserializeError(err, body))?;
+
+        Ok(deserialized.nodegroup)
+    }
+
+    /// Create a nodegroup with taints.
+    pub(crate) fn create_nodegroup(
+        &self,
+        cluster_id: &str,
+        opts: &CreateNodegroupOpts,
+    ) -> Result<(), Error> {
+        let path = format!(
+            "/{}/{}/{}/{}",
+            API_VERSION, CLUSTERS, cluster_id, NODEGROUPS
+        );
+        let body = serde_json::to_string(&NodegroupRoot { nodegroup: opts })
+            .map_err(Error::SerializeError)?;
+        let req = self.new_request(Method::POST, &path, Some(body))?;
+
+        self.do_request(req).map(|_| ())
+    }
+
+    /// Update nodegroup labels, taints and autoscaling bounds.
+    pub(crate) fn update_nodegroup(
+        &self,
+        cluster_id: &str,
+        nodegroup_id: &str,
+        opts: &UpdateNodegroupOpts,
+    ) -> Result<(), Error> {
+        let path = format!(
+            "/{}/{}/{}/{}/{}",
+            API_VERSION, CLUSTERS, cluster_id, NODEGROUPS, nodegroup_id
+        );
+        let body = serde_json::to_string(&NodegroupRoot { nodegroup: opts })
+            .map_err(Error::SerializeError)?;
+        let req = self.new_request(Method::PUT, &path, Some(body))?;
+
+        self.do_request(req).map(|_| ())
+    }
+
+    fn new_request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<String>,
+    ) -> Result<Request<hyper::Body>, Error> {
+        let uri = Uri::builder()
+            .scheme(self.base_endpoint.scheme_str().unwrap_or("https"))
+            .authority(
+                self.base_endpoint
+                    .authority()
+                    .map(|authority| authority.as_str())
+                    .unwrap_or_default(),
+            )
+            .path_and_query(path)
+            .build()
+            .map_err(|_| Error::UrlError)?;
+
+        let mut req = Request::new(hyper::Body::empty());
+        *req.method_mut() = method;
+        *req.uri_mut() = uri;
+
+        req.headers_mut().insert(
+            USER_AGENT,
+            HeaderValue::from_str(&format!("{}/{}", PKG_NAME, PKG_VERSION))
+                .map_err(|_| Error::RequestError)?,
+        );
+        req.headers_mut().insert(
+            "x-auth-token",
+            HeaderValue::from_str(&self.token).map_err(|_| Error::RequestError)?,
+        );
+
+        if let Some(body) = body {
+            let len =
+                HeaderValue::from_str(&body.len().to_string()).map_err(|_| Error::RequestError)?;
+            req.headers_mut().insert(CONTENT_LENGTH, len);
+            req.headers_mut()
+                .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+            *req.body_mut() = hyper::Body::from(body);
+        }
+
+        Ok(req)
+    }
+
+    #[tokio::main]
+    async fn do_request(&self, req: Request<hyper::Body>) -> Result<String, Error> {
+        let handle = async {
+            let raw_resp = self.client.request(req).await?;
+
+            let status = raw_resp.status();
+            let body = hyper::body::aggregate(raw_resp).await?.to_bytes();
+            let body = String::from_utf8_lossy(&body).to_string();
+
+            Ok::<_, hyper::Error>((body, status))
+        };
+
+        let (body, status) = timeout(TIMEOUT, handle)
+            .await
+            .map_err(|_| Error::TimeoutError)??;
+
+        if !status.is_success() {
+            return Err(Error::HttpError(status.as_u16(), body));
+        }
+
+        Ok(body)
+    }
+}
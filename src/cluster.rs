@@ -1,204 +1,648 @@
 use anyhow::{bail, Context, Result};
 use selectel_mks::cluster;
+use selectel_mks::error::Error;
+use selectel_mks::task::schemas::Type as TaskType;
 use selectel_mks::Client;
+use std::str::FromStr;
 use term_table::row::Row;
-use term_table::table_cell::{Alignment, TableCell};
+use term_table::table_cell::TableCell;
 use term_table::{Table, TableStyle};
 
+use crate::api;
+use crate::conf;
+use crate::confirm;
 use crate::json;
+use crate::kubeconfig;
+use crate::logging;
+use crate::manifest;
+use crate::output;
+use crate::task;
+use crate::wait;
+use crate::yaml;
+
+pub(crate) fn get(client: &Client, format: &output::Format, cluster_id: &str) -> Result<()> {
+    let cluster = logging::call("get_cluster", &format!("cluster_id={}", cluster_id), || {
+        client.get_cluster(cluster_id)
+    })
+    .context("Failed to get cluster")?;
+
+    output::print(format, &cluster)
+}
 
-pub(crate) fn get(client: &Client, output: &str, cluster_id: &str) -> Result<()> {
-    let cluster = client
-        .get_cluster(cluster_id)
-        .context("Failed to get cluster")?;
+impl output::Render for cluster::schemas::Cluster {
+    fn print_table(&self) {
+        let updated_at = match &self.updated_at {
+            Some(time) => time.to_rfc3339(),
+            None => String::new(),
+        };
+        let pki_tree_updated_at = match &self.pki_tree_updated_at {
+            Some(time) => time.to_rfc3339(),
+            None => String::new(),
+        };
+        let maintenance_window_start = match &self.maintenance_window_start {
+            Some(time) => time.to_string(),
+            None => String::new(),
+        };
+        let maintenance_window_end = match &self.maintenance_window_end {
+            Some(time) => time.to_string(),
+            None => String::new(),
+        };
+        let maintenance_last_start = match &self.maintenance_last_start {
+            Some(time) => time.to_rfc3339(),
+            None => String::new(),
+        };
+
+        let mut table = Table::new();
+        table.style = TableStyle::simple();
+        table.separate_rows = false;
 
-    match output {
-        "table" => get_print_table(&cluster),
-        "json" => json::print_json(cluster)?,
-        _ => bail!("Unknown output format"),
-    };
+        table.add_row(Row::new(vec![
+            TableCell::new("id"),
+            TableCell::new(&self.id),
+        ]));
 
-    Ok(())
-}
+        table.add_row(Row::new(vec![
+            TableCell::new("created_at"),
+            TableCell::new(self.created_at.to_rfc3339()),
+        ]));
 
-fn get_print_table(cluster: &cluster::schemas::Cluster) {
-    let updated_at = match &cluster.updated_at {
-        Some(time) => time.to_rfc3339(),
-        None => String::new(),
-    };
-    let pki_tree_updated_at = match &cluster.pki_tree_updated_at {
-        Some(time) => time.to_rfc3339(),
-        None => String::new(),
-    };
-    let maintenance_window_start = match &cluster.maintenance_window_start {
-        Some(time) => time.to_string(),
-        None => String::new(),
-    };
-    let maintenance_window_end = match &cluster.maintenance_window_end {
-        Some(time) => time.to_string(),
-        None => String::new(),
-    };
-    let maintenance_last_start = match &cluster.maintenance_last_start {
-        Some(time) => time.to_rfc3339(),
-        None => String::new(),
-    };
+        table.add_row(Row::new(vec![
+            TableCell::new("updated_at"),
+            TableCell::new(&updated_at),
+        ]));
 
-    let mut table = Table::new();
-    table.style = TableStyle::simple();
-    table.separate_rows = false;
-
-    table.add_row(Row::new(vec![
-        TableCell::new("id"),
-        TableCell::new(&cluster.id),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("created_at"),
-        TableCell::new(&cluster.created_at.to_rfc3339()),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("updated_at"),
-        TableCell::new(&updated_at),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("name"),
-        TableCell::new(&cluster.name),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("status"),
-        TableCell::new(&cluster.status),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("project_id"),
-        TableCell::new(&cluster.project_id),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("network_id"),
-        TableCell::new(&cluster.network_id),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("subnet_id"),
-        TableCell::new(&cluster.subnet_id),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("kube_api_ip"),
-        TableCell::new(&cluster.kube_api_ip),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("kube_version"),
-        TableCell::new(&cluster.kube_version),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("region"),
-        TableCell::new(&cluster.region),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("pki_tree_updated_at"),
-        TableCell::new(&pki_tree_updated_at),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("maintenance_window_start"),
-        TableCell::new(&maintenance_window_start),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("maintenance_window_end"),
-        TableCell::new(&maintenance_window_end),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("maintenance_last_start"),
-        TableCell::new(&maintenance_last_start),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("enable_autorepair"),
-        TableCell::new(&cluster.enable_autorepair),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("enable_patch_version_auto_upgrade"),
-        TableCell::new(&cluster.enable_patch_version_auto_upgrade),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("zonal"),
-        TableCell::new(&cluster.zonal),
-    ]));
-
-    println!("{}", table.render());
-}
-
-pub(crate) fn list(client: &Client, output: &str) -> Result<()> {
-    let clusters = client.list_clusters().context("Failed to list clusters")?;
-
-    match output {
-        "table" => list_print_table(&clusters),
-        "json" => json::print_json(clusters)?,
-        _ => bail!("Unknown output format"),
-    };
+        table.add_row(Row::new(vec![
+            TableCell::new("name"),
+            TableCell::new(&self.name),
+        ]));
 
-    Ok(())
-}
+        table.add_row(Row::new(vec![
+            TableCell::new("status"),
+            TableCell::new(&self.status),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("project_id"),
+            TableCell::new(&self.project_id),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("network_id"),
+            TableCell::new(&self.network_id),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("subnet_id"),
+            TableCell::new(&self.subnet_id),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("kube_api_ip"),
+            TableCell::new(&self.kube_api_ip),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("kube_version"),
+            TableCell::new(&self.kube_version),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("region"),
+            TableCell::new(&self.region),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("pki_tree_updated_at"),
+            TableCell::new(&pki_tree_updated_at),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("maintenance_window_start"),
+            TableCell::new(&maintenance_window_start),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("maintenance_window_end"),
+            TableCell::new(&maintenance_window_end),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("maintenance_last_start"),
+            TableCell::new(&maintenance_last_start),
+        ]));
 
-fn list_print_table(clusters: &[cluster::schemas::Cluster]) {
-    let mut table = Table::new();
-    table.style = TableStyle::simple();
+        table.add_row(Row::new(vec![
+            TableCell::new("enable_autorepair"),
+            TableCell::new(self.enable_autorepair),
+        ]));
 
-    table.add_row(Row::new(vec![
-        TableCell::new_with_alignment("id", 1, Alignment::Center),
-        TableCell::new_with_alignment("name", 1, Alignment::Center),
-        TableCell::new_with_alignment("kube_version", 1, Alignment::Center),
-        TableCell::new_with_alignment("kube_api_ip", 1, Alignment::Center),
-        TableCell::new_with_alignment("status", 1, Alignment::Center),
-    ]));
+        table.add_row(Row::new(vec![
+            TableCell::new("enable_patch_version_auto_upgrade"),
+            TableCell::new(self.enable_patch_version_auto_upgrade),
+        ]));
 
-    for cluster in clusters.iter() {
         table.add_row(Row::new(vec![
-            TableCell::new(&cluster.id),
-            TableCell::new(&cluster.name),
-            TableCell::new(&cluster.kube_version),
-            TableCell::new(&cluster.kube_api_ip),
-            TableCell::new(&cluster.status),
+            TableCell::new("zonal"),
+            TableCell::new(self.zonal),
         ]));
+
+        println!("{}", table.render());
+    }
+
+    fn names(&self) -> Option<Vec<String>> {
+        Some(vec![format!("cluster/{}", self.id)])
+    }
+}
+
+/// Order of the listed clusters.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum SortBy {
+    CreatedAt,
+    Name,
+    Status,
+}
+
+impl FromStr for SortBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "created_at" => Ok(SortBy::CreatedAt),
+            "name" => Ok(SortBy::Name),
+            "status" => Ok(SortBy::Status),
+            _ => Err(format!(
+                "unknown sort field \"{}\", can be either of created_at, name or status",
+                s
+            )),
+        }
+    }
+}
+
+pub(crate) fn list(
+    client: &Client,
+    format: &output::Format,
+    table_opts: &output::TableOpts,
+    filters: &[output::Filter],
+    sort_by: Option<SortBy>,
+) -> Result<()> {
+    output::check_filters::<cluster::schemas::Cluster>(filters)?;
+
+    let clusters = logging::call("list_clusters", "", || client.list_clusters())
+        .context("Failed to list clusters")?;
+
+    let mut clusters = output::filter(clusters, filters)?;
+    match sort_by {
+        Some(SortBy::CreatedAt) => clusters.sort_by_key(|cluster| cluster.created_at),
+        Some(SortBy::Name) => clusters.sort_by(|a, b| a.name.cmp(&b.name)),
+        Some(SortBy::Status) => clusters.sort_by_key(|cluster| cluster.status.to_string()),
+        None => (),
+    };
+
+    output::print_list(format, table_opts, &clusters)
+}
+
+impl output::Item for cluster::schemas::Cluster {
+    const KIND: &'static str = "cluster";
+
+    fn id(&self) -> &str {
+        &self.id
     }
 
-    println!("{}", table.render());
+    fn fields() -> Vec<output::Field<Self>> {
+        vec![
+            output::Field::new("id", |cluster| cluster.id.clone()),
+            output::Field::new("name", |cluster| cluster.name.clone()),
+            output::Field::new("kube_version", |cluster| cluster.kube_version.clone()),
+            output::Field::new("kube_api_ip", |cluster| cluster.kube_api_ip.clone()),
+            output::Field::new("status", |cluster| cluster.status.to_string()),
+            output::Field::wide("region", |cluster| cluster.region.clone()),
+            output::Field::wide("zonal", |cluster| cluster.zonal.to_string()),
+            output::Field::wide("maintenance_window_start", |cluster| {
+                cluster.maintenance_window_start.clone().unwrap_or_default()
+            }),
+            output::Field::wide("maintenance_window_end", |cluster| {
+                cluster.maintenance_window_end.clone().unwrap_or_default()
+            }),
+            output::Field::wide("enable_autorepair", |cluster| {
+                cluster.enable_autorepair.to_string()
+            }),
+            output::Field::wide("enable_patch_version_auto_upgrade", |cluster| {
+                cluster.enable_patch_version_auto_upgrade.to_string()
+            }),
+            output::Field::wide("created_at", |cluster| cluster.created_at.to_rfc3339()),
+            output::Field::wide("updated_at", |cluster| {
+                cluster
+                    .updated_at
+                    .map(|time| time.to_rfc3339())
+                    .unwrap_or_default()
+            }),
+            output::Field::wide("project_id", |cluster| cluster.project_id.clone()),
+            output::Field::wide("network_id", |cluster| cluster.network_id.clone()),
+            output::Field::wide("subnet_id", |cluster| cluster.subnet_id.clone()),
+        ]
+    }
 }
 
 pub(crate) fn create(
     client: &Client,
-    output: &str,
+    format: &output::Format,
     opts: cluster::schemas::CreateOpts,
+    wait_opts: Option<wait::WaitOpts>,
 ) -> Result<()> {
-    let cluster = client
-        .create_cluster(&opts)
+    logging::dump("create_cluster request", &opts);
+    let mut cluster = logging::call("create_cluster", "", || client.create_cluster(&opts))
         .context("Failed to create cluster")?;
 
-    match output {
-        "table" => get_print_table(&cluster),
-        "json" => json::print_json(cluster)?,
-        _ => bail!("Unknown output format"),
+    if let Some(wait_opts) = wait_opts {
+        cluster = wait_active(client, &cluster.id, &wait_opts)?;
+    }
+
+    output::print(format, &cluster)
+}
+
+/// Poll the cluster until it becomes ACTIVE, printing every status transition to stderr.
+pub(crate) fn wait_active(
+    client: &Client,
+    cluster_id: &str,
+    wait_opts: &wait::WaitOpts,
+) -> Result<cluster::schemas::Cluster> {
+    let mut last_status = String::new();
+    let what = format!("cluster {} to become ACTIVE", cluster_id);
+
+    wait::poll(wait_opts, &what, || {
+        let cluster = logging::call("get_cluster", &format!("cluster_id={}", cluster_id), || {
+            client.get_cluster(cluster_id)
+        })
+        .context("Failed to get cluster")?;
+
+        let status = cluster.status.to_string();
+        if status != last_status {
+            eprintln!("cluster {}: {}", cluster_id, status);
+            last_status = status;
+        }
+
+        match cluster.status {
+            cluster::schemas::Status::Active => Ok(Some(cluster)),
+            cluster::schemas::Status::Error => bail!("Cluster {} is in ERROR state", cluster_id),
+            _ => Ok(None),
+        }
+    })
+}
+
+/// Update cluster parameters and print the updated cluster.
+pub(crate) fn set(
+    client: &Client,
+    api_client: &api::Client,
+    format: &output::Format,
+    cluster_id: &str,
+    opts: api::UpdateClusterOpts,
+) -> Result<()> {
+    if opts.maintenance_window_start.is_none()
+        && opts.enable_autorepair.is_none()
+        && opts.enable_patch_version_auto_upgrade.is_none()
+    {
+        bail!(
+            "Nothing to update, provide --maintenance-window-start, --enable-autorepair \
+             or --enable-patch-version-auto-upgrade"
+        );
+    }
+
+    logging::dump("update_cluster request", &opts);
+    logging::call(
+        "update_cluster",
+        &format!("cluster_id={}", cluster_id),
+        || api_client.update_cluster(cluster_id, &opts),
+    )
+    .context("Failed to update cluster")?;
+
+    get(client, format, cluster_id)
+}
+
+/// Check that the maintenance window start is a valid UTC time in the "hh:mm:ss" format.
+pub(crate) fn check_maintenance_window(value: String) -> Result<(), String> {
+    let parts: Vec<&str> = value.split(':').collect();
+    let valid = parts.len() == 3
+        && parts
+            .iter()
+            .all(|part| part.len() == 2 && part.chars().all(|c| c.is_ascii_digit()))
+        && parts[0] < "24"
+        && parts[1] < "60"
+        && parts[2] < "60";
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid time \"{}\", expected UTC time in the hh:mm:ss format",
+            value
+        ))
+    }
+}
+
+pub(crate) fn kubeconfig(
+    client: &Client,
+    api_client: &api::Client,
+    cluster_id: &str,
+    target: kubeconfig::Target,
+) -> Result<()> {
+    let config = logging::call_secret(
+        "get_kubeconfig",
+        &format!("cluster_id={}", cluster_id),
+        || api_client.get_kubeconfig(cluster_id),
+    )
+    .context("Failed to get cluster kubeconfig")?;
+
+    match target {
+        kubeconfig::Target::Stdout => print!("{}", config),
+        kubeconfig::Target::File(path) => kubeconfig::write(&path, &config)?,
+        kubeconfig::Target::Merge {
+            path,
+            context,
+            use_context,
+        } => {
+            let context = match context {
+                Some(context) => context,
+                None => {
+                    logging::call("get_cluster", &format!("cluster_id={}", cluster_id), || {
+                        client.get_cluster(cluster_id)
+                    })
+                    .context("Failed to get cluster")?
+                    .name
+                }
+            };
+
+            kubeconfig::merge(&path, &config, &context, use_context)?;
+            eprintln!("Merged context {} into {}", context, path.display());
+        }
+    };
+
+    Ok(())
+}
+
+pub(crate) fn export(client: &Client, format: &output::Format, cluster_id: &str) -> Result<()> {
+    let cluster = logging::call("get_cluster", &format!("cluster_id={}", cluster_id), || {
+        client.get_cluster(cluster_id)
+    })
+    .context("Failed to get cluster")?;
+    let nodegroups = logging::call(
+        "list_nodegroups",
+        &format!("cluster_id={}", cluster_id),
+        || client.list_nodegroups(cluster_id),
+    )
+    .context("Failed to list nodegroups")?;
+
+    let spec = manifest::ClusterSpec::from_existing(&cluster, &nodegroups);
+
+    match format {
+        output::Format::Yaml => yaml::print_yaml(spec)?,
+        output::Format::Json => json::print_json(spec)?,
+        _ => check_export_format(format)?,
     };
 
     Ok(())
 }
 
-pub(crate) fn delete(client: &Client, cluster_id: &str) -> Result<()> {
-    client
-        .delete_cluster(cluster_id)
-        .context("Failed to delete cluster")?;
+/// Check that the manifest can be exported in the output format, it's done before any API calls.
+pub(crate) fn check_export_format(format: &output::Format) -> Result<()> {
+    match format {
+        output::Format::Yaml | output::Format::Json => Ok(()),
+        _ => bail!("Manifest can be exported only as yaml or json"),
+    }
+}
+
+/// Parameters of a cluster copy.
+#[derive(Debug)]
+pub(crate) struct CloneOpts {
+    pub(crate) name: String,
+
+    /// Region of the copy, it's used to relocate availability zones and volume types.
+    pub(crate) region: Option<String>,
+
+    pub(crate) kube_version: Option<String>,
+    pub(crate) network_id: Option<String>,
+    pub(crate) subnet_id: Option<String>,
+}
+
+/// Clients of the project and region where the cluster copy is created.
+pub(crate) struct CloneTarget<'a> {
+    pub(crate) client: &'a Client,
+    pub(crate) api_client: &'a api::Client,
+}
+
+/// Create a copy of the cluster with all of its nodegroups.
+///
+/// Nodegroups are added after the new cluster becomes ACTIVE with the raw API clients, since their
+/// taints, autoscaling, keypairs and affinity policies are not supported by the MKS client library.
+/// Networks are not copied since they belong to the source project and region.
+pub(crate) fn clone(
+    client: &Client,
+    api_client: &api::Client,
+    target: CloneTarget,
+    format: &output::Format,
+    cluster_id: &str,
+    clone_opts: CloneOpts,
+    wait_opts: &wait::WaitOpts,
+) -> Result<()> {
+    let source = logging::call("get_cluster", &format!("cluster_id={}", cluster_id), || {
+        client.get_cluster(cluster_id)
+    })
+    .context("Failed to get cluster")?;
+    let nodegroups = logging::call(
+        "list_nodegroups",
+        &format!("cluster_id={}", cluster_id),
+        || client.list_nodegroups(cluster_id),
+    )
+    .context("Failed to list nodegroups")?;
+    let details = nodegroups
+        .iter()
+        .map(|nodegroup| {
+            logging::call(
+                "get_nodegroup_details",
+                &format!("cluster_id={} nodegroup_id={}", cluster_id, nodegroup.id),
+                || api_client.get_nodegroup_details(cluster_id, &nodegroup.id),
+            )
+            .context("Failed to get nodegroup")
+        })
+        .collect::<Result<Vec<api::NodegroupDetails>>>()?;
+
+    let mut spec = manifest::ClusterSpec::from_existing(&source, &nodegroups);
+    for (nodegroup_spec, details) in spec.nodegroups.iter_mut().zip(details.iter()) {
+        nodegroup_spec.keypair_name = details.keypair_name.clone();
+        nodegroup_spec.affinity_policy = details.affinity_policy.clone();
+    }
+    let region = clone_opts.region.unwrap_or_else(|| source.region.clone());
+    spec.name = clone_opts.name;
+    spec.region = Some(region.clone());
+    spec.network_id = clone_opts.network_id;
+    spec.subnet_id = clone_opts.subnet_id;
+    if let Some(kube_version) = clone_opts.kube_version {
+        spec.kube_version = kube_version;
+    }
+
+    if region != source.region {
+        eprintln!(
+            "Relocating nodegroups from {} to {}, their flavors and keypairs must exist in the \
+             target region",
+            source.region, region
+        );
+        for nodegroup_spec in spec.nodegroups.iter_mut() {
+            nodegroup_spec.availability_zone = nodegroup_spec
+                .availability_zone
+                .replace(&source.region, &region);
+            nodegroup_spec.volume_type = nodegroup_spec
+                .volume_type
+                .as_ref()
+                .map(|volume_type| volume_type.replace(&source.region, &region));
+        }
+    }
+
+    let nodegroup_specs = std::mem::take(&mut spec.nodegroups);
+    let opts = spec.create_opts(&region);
+    logging::dump("create_cluster request", &opts);
+    let created = logging::call("create_cluster", "", || target.client.create_cluster(&opts))
+        .context("Failed to create cluster")?;
+    eprintln!("Created cluster {} ({})", created.name, created.id);
+
+    let mut cluster = wait_active(target.client, &created.id, wait_opts)?;
+    for (nodegroup_spec, details) in nodegroup_specs.iter().zip(details.iter()) {
+        let opts = nodegroup_spec.create_opts();
+        // Autoscaling bounds are only sent with autoscaling enabled.
+        let (enable_autoscale, autoscale_min_nodes, autoscale_max_nodes) =
+            if details.enable_autoscale {
+                (
+                    Some(true),
+                    details.autoscale_min_nodes,
+                    details.autoscale_max_nodes,
+                )
+            } else {
+                (None, None, None)
+            };
+        let opts = api::CreateNodegroupOpts {
+            opts: &opts,
+            taints: &details.taints,
+            enable_autoscale,
+            autoscale_min_nodes,
+            autoscale_max_nodes,
+        };
+        logging::dump("create_nodegroup request", &opts);
+        logging::call(
+            "create_nodegroup",
+            &format!("cluster_id={}", created.id),
+            || target.api_client.create_nodegroup(&created.id, &opts),
+        )
+        .context("Failed to create nodegroup")?;
+        eprintln!(
+            "Created nodegroup in {} with {} node(s)",
+            nodegroup_spec.availability_zone, nodegroup_spec.nodes_count
+        );
+
+        cluster = wait_active(target.client, &created.id, wait_opts)?;
+    }
+
+    output::print(format, &cluster)
+}
+
+/// Delete the cluster and print its deletion task.
+///
+/// The cluster name must be typed to confirm the deletion unless `yes` is set.
+/// Tasks of a deleted cluster may be unavailable, so waiting polls the cluster until it's gone.
+pub(crate) fn delete(
+    client: &Client,
+    format: &output::Format,
+    cluster_id: &str,
+    yes: bool,
+    wait_args: &conf::WaitArgs,
+) -> Result<()> {
+    if !yes {
+        confirm_delete(client, cluster_id)?;
+    }
+    let wait_opts = wait::WaitOpts::new(wait_args.timeout, wait_args.poll_interval);
+
+    let known_tasks = task::ids(client, cluster_id)?;
+    logging::call(
+        "delete_cluster",
+        &format!("cluster_id={}", cluster_id),
+        || client.delete_cluster(cluster_id),
+    )
+    .context("Failed to delete cluster")?;
+
+    let tasks = task::find_started(
+        client,
+        cluster_id,
+        &known_tasks,
+        &TaskType::DeleteCluster,
+        wait_args,
+    )?;
+    task::print_started(format, &tasks)?;
+
+    if wait_args.wait {
+        wait_deleted(client, cluster_id, &wait_opts)?;
+    }
+
+    Ok(())
+}
+
+/// Print the cluster summary and ask to type its name.
+fn confirm_delete(client: &Client, cluster_id: &str) -> Result<()> {
+    let cluster = logging::call("get_cluster", &format!("cluster_id={}", cluster_id), || {
+        client.get_cluster(cluster_id)
+    })
+    .context("Failed to get cluster")?;
+    let nodegroups = logging::call(
+        "list_nodegroups",
+        &format!("cluster_id={}", cluster_id),
+        || client.list_nodegroups(cluster_id),
+    )
+    .context("Failed to list nodegroups")?;
+    let nodes_count: usize = nodegroups
+        .iter()
+        .map(|nodegroup| nodegroup.nodes.len())
+        .sum();
+
+    eprintln!(
+        "Cluster {} ({}) in {} is {} old, it has {} nodegroup(s) with {} node(s)",
+        cluster.name,
+        cluster.id,
+        cluster.region,
+        confirm::age(cluster.created_at.timestamp()),
+        nodegroups.len(),
+        nodes_count
+    );
+    if !confirm::confirm("Delete the cluster", Some(&cluster.name))? {
+        bail!("Cluster name doesn't match, deletion is cancelled");
+    }
 
     Ok(())
 }
+
+/// Poll the cluster until it's not found, printing every status transition to stderr.
+fn wait_deleted(client: &Client, cluster_id: &str, wait_opts: &wait::WaitOpts) -> Result<()> {
+    let mut last_status = String::new();
+    let what = format!("cluster {} to be deleted", cluster_id);
+
+    wait::poll(wait_opts, &what, || {
+        let result = logging::call("get_cluster", &format!("cluster_id={}", cluster_id), || {
+            client.get_cluster(cluster_id)
+        });
+
+        let cluster = match result {
+            Ok(cluster) => cluster,
+            Err(Error::HttpError(404, _)) => {
+                eprintln!("cluster {}: DELETED", cluster_id);
+                return Ok(Some(()));
+            }
+            Err(err) => return Err(err).context("Failed to get cluster"),
+        };
+
+        let status = cluster.status.to_string();
+        if status != last_status {
+            eprintln!("cluster {}: {}", cluster_id, status);
+            last_status = status;
+        }
+
+        match cluster.status {
+            cluster::schemas::Status::Error => bail!("Cluster {} is in ERROR state", cluster_id),
+            _ => Ok(None),
+        }
+    })
+}
@@ -0,0 +1,221 @@
+use anyhow::{bail, Context, Result};
+use ring::digest;
+use selectel_mks::Client;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+use structopt::clap::Shell;
+use structopt::StructOpt;
+
+use crate::conf;
+use crate::logging;
+use crate::resolve;
+
+/// Time to reuse the cached completion values, it saves API calls on repeated TAB presses.
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Bash completion of the cluster and nodegroup identifiers, it falls back to the generated one.
+const BASH_DYNAMIC: &str = r#"
+_mks_dynamic() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" prev="${COMP_WORDS[COMP_CWORD-1]}"
+    local command="${COMP_WORDS[1]} ${COMP_WORDS[2]}" cluster="" kind="" i
+    for ((i = 1; i < COMP_CWORD - 1; i++)); do
+        if [[ ${COMP_WORDS[i]} == --cluster-id ]]; then
+            cluster="${COMP_WORDS[i+1]}"
+        fi
+    done
+
+    if [[ ${prev} == --cluster-id ]]; then
+        kind="clusters"
+    elif [[ ${prev} == --nodegroup-id ]]; then
+        kind="nodegroups"
+    elif [[ ${cur} != -* && ( ${COMP_CWORD} -eq 3 || ${COMP_WORDS[COMP_CWORD-2]} == --cluster-id ) ]]; then
+        case "${command}" in
+            "cluster get"|"cluster set"|"cluster delete"|"cluster export"|"cluster kubeconfig"|"cluster clone")
+                kind="clusters" ;;
+            "nodegroup get"|"nodegroup set"|"nodegroup delete"|"nodegroup reinstall")
+                kind="nodegroups" ;;
+        esac
+    fi
+
+    if [[ ${kind} == clusters ]]; then
+        COMPREPLY=($(compgen -W "$(mks complete clusters 2>/dev/null)" -- "${cur}"))
+        return 0
+    elif [[ ${kind} == nodegroups && -n ${cluster} ]]; then
+        COMPREPLY=($(compgen -W "$(mks complete nodegroups --cluster-id "${cluster}" 2>/dev/null)" -- "${cur}"))
+        return 0
+    fi
+
+    _mks "$@"
+}
+
+complete -F _mks_dynamic -o bashdefault -o default mks
+"#;
+
+/// Zsh completers used by the cluster and nodegroup arguments instead of files.
+const ZSH_DYNAMIC: &str = r#"
+_mks_clusters() {
+    compadd -- ${(f)"$(mks complete clusters 2>/dev/null)"}
+}
+
+_mks_nodegroups() {
+    local cluster="${opt_args[--cluster-id]}"
+    [[ -n ${cluster} ]] && compadd -- ${(f)"$(mks complete nodegroups --cluster-id "${cluster}" 2>/dev/null)"}
+}
+
+"#;
+
+/// Fish completions of the cluster and nodegroup identifiers.
+const FISH_DYNAMIC: &str = r#"
+function __mks_nodegroups
+    set -l words (commandline -opc)
+    set -l index (contains -i -- --cluster-id $words)
+    and mks complete nodegroups --cluster-id $words[(math $index + 1)] 2>/dev/null
+end
+
+complete -c mks -l cluster-id -x -a '(mks complete clusters 2>/dev/null)'
+complete -c mks -l nodegroup-id -x -a '(__mks_nodegroups)'
+complete -c mks -n '__fish_seen_subcommand_from cluster; and __fish_seen_subcommand_from get set delete export kubeconfig clone' -f -a '(mks complete clusters 2>/dev/null)'
+complete -c mks -n '__fish_seen_subcommand_from nodegroup; and __fish_seen_subcommand_from get set delete reinstall' -f -a '(__mks_nodegroups)'
+"#;
+
+/// Print the completion script generated from the options.
+///
+/// Bash, zsh and fish scripts also complete cluster and nodegroup identifiers with the hidden
+/// `complete` command, PowerShell gets only the generated one.
+pub(crate) fn print_script(shell: Shell) -> Result<()> {
+    let mut script = Vec::new();
+    conf::CliOptions::clap().gen_completions_to("mks", shell, &mut script);
+    let script = String::from_utf8(script).context("Failed to generate completion script")?;
+
+    match shell {
+        Shell::Bash => print!("{}{}", script, BASH_DYNAMIC),
+        Shell::Zsh => {
+            let script: Vec<String> = script.lines().map(zsh_line).collect();
+            let script = script.join("\n");
+            match script.rfind("_mks \"$@\"") {
+                Some(call) => println!("{}{}{}", &script[..call], ZSH_DYNAMIC, &script[call..]),
+                None => println!("{}", script),
+            }
+        }
+        Shell::Fish => print!("{}{}", script, FISH_DYNAMIC),
+        _ => print!("{}", script),
+    }
+
+    Ok(())
+}
+
+/// Use the dynamic completers for cluster and nodegroup options and arguments in the zsh script.
+fn zsh_line(line: &str) -> String {
+    for (arg, completer) in [
+        ("cluster-id", "_mks_clusters"),
+        ("nodegroup-id", "_mks_nodegroups"),
+    ] {
+        if line.starts_with(&format!("'--{}=[", arg)) && line.ends_with("]' \\") {
+            let spec = line.trim_end_matches("' \\");
+            return format!("{}:{}:{}' \\", spec, arg, completer);
+        }
+        if line.starts_with(&format!("':{} -- ", arg)) && line.ends_with(":_files' \\") {
+            let spec = line.trim_end_matches("_files' \\");
+            return format!("{}{}' \\", spec, completer);
+        }
+    }
+
+    line.to_string()
+}
+
+/// Print cluster identifiers and names or nodegroup identifiers of the cluster one per line.
+///
+/// Values are cached for every endpoint and token, so different projects don't share them.
+pub(crate) fn complete(
+    client: &Client,
+    credentials: &[&str],
+    kind: &str,
+    cluster_id: Option<&str>,
+) -> Result<()> {
+    let cache_path = dirs::cache_dir().map(|dir| {
+        dir.join("mks")
+            .join("completion")
+            .join(cache_name(credentials, kind, cluster_id))
+    });
+
+    if let Some(values) = cache_path.as_ref().and_then(read_cache) {
+        print!("{}", values);
+        return Ok(());
+    }
+
+    let values = match kind {
+        "clusters" => {
+            let clusters = logging::call("list_clusters", "", || client.list_clusters())
+                .context("Failed to list clusters")?;
+            clusters
+                .into_iter()
+                .flat_map(|cluster| vec![cluster.id, cluster.name])
+                .collect::<Vec<_>>()
+        }
+        "nodegroups" => {
+            let cluster_id = cluster_id.context("Cluster is required to complete nodegroups")?;
+            let cluster_id = resolve::cluster(client, cluster_id)?;
+            let nodegroups = logging::call(
+                "list_nodegroups",
+                &format!("cluster_id={}", cluster_id),
+                || client.list_nodegroups(&cluster_id),
+            )
+            .context("Failed to list nodegroups")?;
+            nodegroups
+                .into_iter()
+                .map(|nodegroup| nodegroup.id)
+                .collect()
+        }
+        _ => bail!(
+            "Unknown completion kind \"{}\", expected clusters or nodegroups",
+            kind
+        ),
+    };
+
+    let mut values = values;
+    values.sort();
+    values.dedup();
+    let mut values = values.join("\n");
+    values.push('\n');
+    if let Some(path) = cache_path {
+        // The cache is an optimization, completion works without it.
+        let _ = write_cache(&path, &values);
+    }
+    print!("{}", values);
+
+    Ok(())
+}
+
+/// Get the cache file name, it's a SHA-256 prefix that is stable across builds and doesn't
+/// reveal the token.
+fn cache_name(credentials: &[&str], kind: &str, cluster_id: Option<&str>) -> String {
+    let mut key = credentials.to_vec();
+    key.push(kind);
+    key.push(cluster_id.unwrap_or_default());
+
+    digest::digest(&digest::SHA256, key.join("\0").as_bytes())
+        .as_ref()
+        .iter()
+        .take(16)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn read_cache(path: &PathBuf) -> Option<String> {
+    let age = fs::metadata(path).ok()?.modified().ok()?.elapsed().ok()?;
+    if age > CACHE_TTL {
+        return None;
+    }
+
+    fs::read_to_string(path).ok()
+}
+
+fn write_cache(path: &PathBuf, values: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, values)
+}
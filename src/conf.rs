@@ -1,5 +1,11 @@
+use structopt::clap::{AppSettings, Shell};
 use structopt::StructOpt;
 
+use crate::cluster::{check_maintenance_window, SortBy};
+use crate::nodegroup::{Label, Taint};
+use crate::output::{Filter, Format};
+use crate::wait::check_poll_interval;
+
 #[derive(Debug, StructOpt)]
 #[structopt(name = "mks")]
 pub(crate) struct CliOptions {
@@ -12,12 +18,30 @@ pub(crate) struct CliOptions {
     pub(crate) verbose: bool,
 
     #[structopt(long, env = "MKS_TOKEN", hide_env_values = true)]
-    /// MKS project-scoped token
-    pub(crate) mks_token: String,
+    /// MKS project-scoped token, overrides the profile token
+    pub(crate) mks_token: Option<String>,
 
     #[structopt(long, env = "MKS_ENDPOINT", hide_env_values = true)]
-    /// MKS endpoint
-    pub(crate) mks_endpoint: String,
+    /// MKS endpoint, overrides the profile endpoint
+    pub(crate) mks_endpoint: Option<String>,
+
+    #[structopt(long, env = "MKS_REGION")]
+    /// MKS region, used to get the endpoint when it's not provided
+    pub(crate) region: Option<String>,
+
+    #[structopt(long, env = "MKS_PROFILE")]
+    /// Configuration profile to use instead of the current one
+    pub(crate) profile: Option<String>,
+
+    #[structopt(short, long, global = true)]
+    /// Output format, can be either of table, wide, json, yaml, name, jsonpath=<template>
+    /// or custom-columns=<header>:<path>,...
+    pub(crate) output: Option<Format>,
+
+    #[structopt(long, env = "MKS_CONFIG")]
+    /// Path to the configuration file, defaults to mks/config.toml in the user configuration
+    /// directory, e.g. ~/.config/mks/config.toml on Linux
+    pub(crate) config: Option<String>,
 
     #[structopt(subcommand)]
     pub(crate) resource: Resource,
@@ -25,9 +49,26 @@ pub(crate) struct CliOptions {
 
 #[derive(Debug, StructOpt)]
 pub(crate) enum Resource {
+    /// Create or update a cluster and its nodegroups from a manifest
+    Apply(Apply),
+
     /// Cluster commands
     Cluster(Cluster),
 
+    /// Print shell completion script
+    Completion(Completion),
+
+    /// Print cluster or nodegroup identifiers for shell completion
+    #[structopt(setting = AppSettings::Hidden)]
+    Complete(Complete),
+
+    /// Configuration profile commands
+    Config(Config),
+
+    /// Show changes that apply would make
+    #[structopt(alias = "plan")]
+    Diff(Diff),
+
     /// Kubeversion commands
     Kubeversion(Kubeversion),
 
@@ -41,6 +82,74 @@ pub(crate) enum Resource {
     Task(Task),
 }
 
+#[derive(Debug, StructOpt)]
+pub(crate) struct Completion {
+    /// Shell to print the script for
+    #[structopt(possible_values = &["bash", "zsh", "fish", "powershell"])]
+    pub(crate) shell: Shell,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct Complete {
+    /// Resources to complete
+    #[structopt(possible_values = &["clusters", "nodegroups"])]
+    pub(crate) kind: String,
+
+    /// Cluster identifier or name, required to complete nodegroups
+    #[structopt(long)]
+    pub(crate) cluster_id: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct Apply {
+    /// YAML or JSON manifest file, use "-" to read it from stdin
+    #[structopt(short, long)]
+    pub(crate) filename: String,
+
+    /// Delete existing nodegroups that are not described in the manifest
+    #[structopt(long)]
+    pub(crate) prune: bool,
+
+    /// Delete nodegroups with --prune without confirmation
+    #[structopt(short, long)]
+    pub(crate) yes: bool,
+
+    /// Maximum time in seconds to wait for the cluster between changes
+    #[structopt(long, default_value = "1800")]
+    pub(crate) timeout: u64,
+
+    /// Interval in seconds between cluster status checks
+    #[structopt(long, default_value = "10", validator = check_poll_interval)]
+    pub(crate) poll_interval: u64,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct ListOpts {
+    /// Comma-separated columns to print instead of the default ones, all columns are
+    /// printed with "-o wide". It's only supported by table output formats
+    #[structopt(long, use_delimiter = true)]
+    pub(crate) columns: Option<Vec<String>>,
+
+    /// Don't print the table headers and borders, it's only supported by table output formats
+    #[structopt(long)]
+    pub(crate) no_headers: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct WaitArgs {
+    /// Wait until the started operation is finished
+    #[structopt(long)]
+    pub(crate) wait: bool,
+
+    /// Maximum time in seconds to wait
+    #[structopt(long, default_value = "1800")]
+    pub(crate) timeout: u64,
+
+    /// Interval in seconds between status checks
+    #[structopt(long, default_value = "10", validator = check_poll_interval)]
+    pub(crate) poll_interval: u64,
+}
+
 #[derive(Debug, StructOpt)]
 pub(crate) struct Cluster {
     #[structopt(subcommand)]
@@ -51,28 +160,28 @@ pub(crate) struct Cluster {
 pub(crate) enum ClusterCommand {
     /// Get cluster
     Get {
-        #[structopt(default_value = "table", short, long)]
-        /// Output format, can be either of table or json
-        output: String,
-
-        /// Cluster identifier
+        /// Cluster identifier or name
         #[structopt(name = "cluster-id")]
         cluster_id: String,
     },
 
     /// List all clusters
     List {
-        #[structopt(default_value = "table", short, long)]
-        /// Output format, can be either of table or json
-        output: String,
+        #[structopt(flatten)]
+        list_opts: ListOpts,
+
+        /// Show only clusters with the field value, "<field>=<value>" matches equal values
+        /// and "<field>~=<value>" matches values containing it, can be repeated
+        #[structopt(long, number_of_values = 1)]
+        filter: Vec<Filter>,
+
+        /// Sort clusters by the field
+        #[structopt(long, possible_values = &["created_at", "name", "status"])]
+        sort_by: Option<SortBy>,
     },
 
     /// Create a new cluster
     Create {
-        #[structopt(default_value = "table", short, long)]
-        /// Output format, can be either of table or json
-        output: String,
-
         /// Cluster name
         #[structopt(long)]
         name: String,
@@ -81,9 +190,9 @@ pub(crate) enum ClusterCommand {
         #[structopt(long)]
         kube_version: String,
 
-        /// Cluster region
+        /// Cluster region, defaults to the region of the MKS endpoint
         #[structopt(long)]
-        region: String,
+        region: Option<String>,
 
         /// Reference to a pre-created network
         #[structopt(long)]
@@ -94,7 +203,7 @@ pub(crate) enum ClusterCommand {
         subnet_id: Option<String>,
 
         /// UTC time in "hh:mm:ss" format of when the cluster will start its maintenance tasks
-        #[structopt(long)]
+        #[structopt(long, validator = check_maintenance_window)]
         maintenance_window_start: Option<String>,
 
         /// Flag that indicates if worker nodes are allowed to be reinstalled automatically
@@ -111,16 +220,183 @@ pub(crate) enum ClusterCommand {
         /// control-plane is not in highly available mode
         #[structopt(long)]
         zonal: Option<bool>,
+
+        #[structopt(flatten)]
+        wait_args: WaitArgs,
+    },
+
+    /// Set cluster parameters
+    Set {
+        /// Cluster identifier or name
+        #[structopt(name = "cluster-id")]
+        cluster_id: String,
+
+        /// UTC time in "hh:mm:ss" format of when the cluster will start its maintenance tasks
+        #[structopt(long, validator = check_maintenance_window)]
+        maintenance_window_start: Option<String>,
+
+        /// Flag that indicates if worker nodes are allowed to be reinstalled automatically
+        /// in case of their unavailability or unhealthiness
+        #[structopt(long)]
+        enable_autorepair: Option<bool>,
+
+        /// Flag that indicates if Kubernetes patch version of the cluster is allowed to be upgraded
+        /// automatically
+        #[structopt(long)]
+        enable_patch_version_auto_upgrade: Option<bool>,
+    },
+
+    /// Get cluster kubeconfig
+    Kubeconfig {
+        /// Cluster identifier or name
+        #[structopt(name = "cluster-id")]
+        cluster_id: String,
+
+        /// Write kubeconfig to the file instead of printing it
+        #[structopt(long, conflicts_with = "merge")]
+        file: Option<String>,
+
+        /// Merge kubeconfig into the kubeconfig file
+        #[structopt(long)]
+        merge: bool,
+
+        /// Kubeconfig file to merge into, defaults to the first file from KUBECONFIG
+        /// or ~/.kube/config
+        #[structopt(long, requires = "merge")]
+        kubeconfig: Option<String>,
+
+        /// Name of the merged context, defaults to the cluster name
+        #[structopt(long, requires = "merge")]
+        context: Option<String>,
+
+        /// Switch the current context to the merged one
+        #[structopt(long, requires = "merge")]
+        use_context: bool,
+    },
+
+    /// Create a copy of the cluster with all of its nodegroups in the same or another
+    /// region or project
+    Clone {
+        /// Cluster identifier or name
+        #[structopt(name = "cluster-id")]
+        cluster_id: String,
+
+        /// Name of the new cluster
+        #[structopt(long)]
+        name: String,
+
+        /// Region of the new cluster, defaults to the region of the source cluster
+        #[structopt(long)]
+        region: Option<String>,
+
+        /// Configuration profile of the project for the new cluster, defaults to the
+        /// source project
+        #[structopt(long)]
+        target_profile: Option<String>,
+
+        /// Kubernetes version of the new cluster, defaults to the source cluster version
+        #[structopt(long)]
+        kube_version: Option<String>,
+
+        /// Reference to a pre-created network for the new cluster
+        #[structopt(long)]
+        network_id: Option<String>,
+
+        /// Reference to a pre-created subnet for the new cluster
+        #[structopt(long)]
+        subnet_id: Option<String>,
+
+        /// Maximum time in seconds to wait for the new cluster between changes
+        #[structopt(long, default_value = "1800")]
+        timeout: u64,
+
+        /// Interval in seconds between cluster status checks
+        #[structopt(long, default_value = "10", validator = check_poll_interval)]
+        poll_interval: u64,
+    },
+
+    /// Export cluster and its nodegroups as a manifest
+    Export {
+        /// Cluster identifier or name
+        #[structopt(name = "cluster-id")]
+        cluster_id: String,
     },
 
     /// Delete cluster
     Delete {
-        /// Cluster identifier
+        /// Cluster identifier or name
         #[structopt(name = "cluster-id")]
         cluster_id: String,
+
+        /// Delete without the confirmation prompt
+        #[structopt(short, long)]
+        yes: bool,
+
+        #[structopt(flatten)]
+        wait_args: WaitArgs,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) struct Config {
+    #[structopt(subcommand)]
+    pub(crate) command: ConfigCommand,
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) enum ConfigCommand {
+    /// Add a new profile
+    Add {
+        /// Profile name
+        #[structopt(name = "name")]
+        name: String,
+
+        /// MKS endpoint
+        #[structopt(long)]
+        endpoint: String,
+
+        /// MKS project-scoped token
+        #[structopt(long)]
+        token: Option<String>,
+
+        /// Shell command that prints MKS project-scoped token
+        #[structopt(long)]
+        token_command: Option<String>,
+
+        /// Make the new profile current
+        #[structopt(long = "use")]
+        set_current: bool,
+    },
+
+    /// List all profiles
+    List {},
+
+    /// Remove profile
+    Remove {
+        /// Profile name
+        #[structopt(name = "name")]
+        name: String,
+    },
+
+    /// Set the current profile
+    Use {
+        /// Profile name
+        #[structopt(name = "name")]
+        name: String,
     },
 }
 
+#[derive(Debug, StructOpt)]
+pub(crate) struct Diff {
+    /// YAML or JSON manifest file, use "-" to read it from stdin
+    #[structopt(short, long)]
+    pub(crate) filename: String,
+
+    /// Show deletion of existing nodegroups that are not described in the manifest
+    #[structopt(long)]
+    pub(crate) prune: bool,
+}
+
 #[derive(Debug, StructOpt)]
 pub(crate) struct Kubeversion {
     #[structopt(subcommand)]
@@ -131,9 +407,8 @@ pub(crate) struct Kubeversion {
 pub(crate) enum KubeversionCommand {
     /// List all available Kubernetes versions
     List {
-        #[structopt(default_value = "table", short, long)]
-        /// Output format, can be either of table or json
-        output: String,
+        #[structopt(flatten)]
+        list_opts: ListOpts,
     },
 }
 
@@ -145,13 +420,23 @@ pub(crate) struct Node {
 
 #[derive(Debug, StructOpt)]
 pub(crate) enum NodeCommand {
+    /// List cluster nodes in all nodegroups or in a single one
+    List {
+        #[structopt(flatten)]
+        list_opts: ListOpts,
+
+        /// Cluster identifier or name
+        #[structopt(long)]
+        cluster_id: String,
+
+        /// Nodegroup identifier, nodes of all nodegroups are listed if it's not provided
+        #[structopt(long)]
+        nodegroup_id: Option<String>,
+    },
+
     /// Get a cluster node in a nodegroup
     Get {
-        #[structopt(default_value = "table", short, long)]
-        /// Output format, can be either of table or json
-        output: String,
-
-        /// Cluster identifier
+        /// Cluster identifier or name
         #[structopt(long)]
         cluster_id: String,
 
@@ -159,14 +444,14 @@ pub(crate) enum NodeCommand {
         #[structopt(long)]
         nodegroup_id: String,
 
-        /// Node identifier
+        /// Node identifier or hostname
         #[structopt(name = "node-id")]
         node_id: String,
     },
 
     /// Reinstall a single cluster node in a nodegroup
     Reinstall {
-        /// Cluster identifier
+        /// Cluster identifier or name
         #[structopt(long)]
         cluster_id: String,
 
@@ -174,9 +459,12 @@ pub(crate) enum NodeCommand {
         #[structopt(long)]
         nodegroup_id: String,
 
-        /// Node identifier
+        /// Node identifier or hostname
         #[structopt(name = "node-id")]
         node_id: String,
+
+        #[structopt(flatten)]
+        wait_args: WaitArgs,
     },
 }
 
@@ -190,33 +478,32 @@ pub(crate) struct Nodegroup {
 pub(crate) enum NodegroupCommand {
     /// List cluster nodegroups
     List {
-        #[structopt(default_value = "table", short, long)]
-        /// Output format, can be either of table or json
-        output: String,
+        #[structopt(flatten)]
+        list_opts: ListOpts,
 
-        /// Cluster identifier
+        /// Cluster identifier or name
         #[structopt(long)]
         cluster_id: String,
     },
 
     /// Get cluster nodegroup
     Get {
-        #[structopt(default_value = "table", short, long)]
-        /// Output format, can be either of table or json
-        output: String,
-
-        /// Cluster identifier
+        /// Cluster identifier or name
         #[structopt(long)]
         cluster_id: String,
 
         /// Nodegroup identifier
         #[structopt(name = "nodegroup-id")]
         nodegroup_id: String,
+
+        /// Print the table of nodegroup nodes, it can be disabled for large nodegroups
+        #[structopt(long, default_value = "true", parse(try_from_str))]
+        show_nodes: bool,
     },
 
     /// Create a new nodegroup
     Create {
-        /// Cluster identifier
+        /// Cluster identifier or name
         #[structopt(long)]
         cluster_id: String,
 
@@ -228,6 +515,15 @@ pub(crate) enum NodegroupCommand {
         #[structopt(long)]
         availability_zone: String,
 
+        /// Kubernetes label of the nodes in the key=value form, can be repeated
+        #[structopt(long, number_of_values = 1)]
+        label: Vec<Label>,
+
+        /// Kubernetes taint of the nodes in the key=value:Effect or key:Effect form, the effect is
+        /// NoSchedule, PreferNoSchedule or NoExecute, can be repeated
+        #[structopt(long, number_of_values = 1)]
+        taint: Vec<Taint>,
+
         /// Use local volume for each node
         #[structopt(long)]
         local_volume: bool,
@@ -263,11 +559,14 @@ pub(crate) enum NodegroupCommand {
         /// Optional parameter to tune nodes affinity
         #[structopt(long)]
         affinity_policy: Option<String>,
+
+        #[structopt(flatten)]
+        wait_args: WaitArgs,
     },
 
-    /// Set nodegroup parameters
+    /// Set nodegroup nodes count, labels, taints and autoscaling bounds
     Set {
-        /// Cluster identifier
+        /// Cluster identifier or name
         #[structopt(long)]
         cluster_id: String,
 
@@ -278,17 +577,80 @@ pub(crate) enum NodegroupCommand {
         /// Count of nodes
         #[structopt(long)]
         nodes_count: Option<u32>,
+
+        /// Kubernetes label of the nodes in the key=value form, can be repeated
+        #[structopt(long, number_of_values = 1)]
+        label: Vec<Label>,
+
+        /// Key of the Kubernetes label to remove, can be repeated
+        #[structopt(long, number_of_values = 1)]
+        remove_label: Vec<String>,
+
+        /// Kubernetes taint of the nodes in the key=value:Effect or key:Effect form, it replaces
+        /// the taint with the same key and effect, can be repeated
+        #[structopt(long, number_of_values = 1)]
+        taint: Vec<Taint>,
+
+        /// Kubernetes taint to remove in the key or key:Effect form, can be repeated
+        #[structopt(long, number_of_values = 1)]
+        remove_taint: Vec<String>,
+
+        /// Flag that indicates if the nodes count is changed automatically within the
+        /// autoscaling bounds
+        #[structopt(long)]
+        enable_autoscale: Option<bool>,
+
+        /// Minimum count of nodes when autoscaling is enabled
+        #[structopt(long)]
+        autoscale_min_nodes: Option<u32>,
+
+        /// Maximum count of nodes when autoscaling is enabled
+        #[structopt(long)]
+        autoscale_max_nodes: Option<u32>,
+
+        #[structopt(flatten)]
+        wait_args: WaitArgs,
+    },
+
+    /// Reinstall all nodes of the nodegroup one batch at a time
+    Reinstall {
+        /// Cluster identifier or name
+        #[structopt(long)]
+        cluster_id: String,
+
+        /// Nodegroup identifier
+        #[structopt(name = "nodegroup-id")]
+        nodegroup_id: String,
+
+        /// Count of nodes that are reinstalled at the same time
+        #[structopt(long, default_value = "1")]
+        max_unavailable: usize,
+
+        /// Maximum time in seconds to wait for every batch of nodes
+        #[structopt(long, default_value = "1800")]
+        timeout: u64,
+
+        /// Interval in seconds between task status checks
+        #[structopt(long, default_value = "10", validator = check_poll_interval)]
+        poll_interval: u64,
     },
 
     /// Delete nodegroup
     Delete {
-        /// Cluster identifier
+        /// Cluster identifier or name
         #[structopt(long)]
         cluster_id: String,
 
         /// Nodegroup identifier
         #[structopt(name = "nodegroup-id")]
         nodegroup_id: String,
+
+        /// Delete without the confirmation prompt
+        #[structopt(short, long)]
+        yes: bool,
+
+        #[structopt(flatten)]
+        wait_args: WaitArgs,
     },
 }
 
@@ -302,22 +664,17 @@ pub(crate) struct Task {
 pub(crate) enum TaskCommand {
     /// List cluster tasks
     List {
-        #[structopt(default_value = "table", short, long)]
-        /// Output format, can be either of table or json
-        output: String,
+        #[structopt(flatten)]
+        list_opts: ListOpts,
 
-        /// Cluster identifier
+        /// Cluster identifier or name
         #[structopt(long)]
         cluster_id: String,
     },
 
     /// Get cluster task
     Get {
-        #[structopt(default_value = "table", short, long)]
-        /// Output format, can be either of table or json
-        output: String,
-
-        /// Cluster identifier
+        /// Cluster identifier or name
         #[structopt(long)]
         cluster_id: String,
 
@@ -325,4 +682,23 @@ pub(crate) enum TaskCommand {
         #[structopt(name = "task-id")]
         task_id: String,
     },
+
+    /// Watch cluster tasks until they're finished, it fails if any of them ends with ERROR
+    Watch {
+        /// Cluster identifier or name
+        #[structopt(long)]
+        cluster_id: String,
+
+        /// Task identifier, all tasks that are in progress are watched if it's not provided
+        #[structopt(name = "task-id")]
+        task_id: Option<String>,
+
+        /// Maximum time in seconds to wait for the tasks
+        #[structopt(long, default_value = "1800")]
+        timeout: u64,
+
+        /// Interval in seconds between task status checks
+        #[structopt(long, default_value = "10", validator = check_poll_interval)]
+        poll_interval: u64,
+    },
 }
@@ -0,0 +1,50 @@
+use anyhow::{bail, Context, Result};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Ask the user to confirm a destructive action on the terminal.
+///
+/// The `expected` answer must be typed if it's provided, otherwise "y" or "yes" is accepted.
+/// It fails when stdin is not a terminal since there is nobody to answer.
+pub(crate) fn confirm(question: &str, expected: Option<&str>) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        bail!("Confirmation is required but stdin is not a terminal, use --yes to skip it");
+    }
+
+    match expected {
+        Some(expected) => eprint!("{}, type \"{}\" to confirm: ", question, expected),
+        None => eprint!("{} [y/N]: ", question),
+    }
+    io::stderr()
+        .flush()
+        .context("Failed to print confirmation")?;
+
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .context("Failed to read confirmation")?;
+    let answer = answer.trim();
+
+    Ok(match expected {
+        Some(expected) => answer == expected,
+        None => answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"),
+    })
+}
+
+/// Format time passed since the UNIX timestamp like "3d4h", "5h12m" or "7m".
+pub(crate) fn age(timestamp: i64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64);
+    let minutes = (now - timestamp).max(0) / 60;
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+
+    if days > 0 {
+        format!("{}d{}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h{}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
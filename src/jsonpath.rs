@@ -0,0 +1,377 @@
+use serde_json::Value;
+use std::convert::TryFrom;
+
+/// Parsed kubectl-style JSONPath template such as `{.id}` or `{range .items[*]}{.id}{"\n"}{end}`.
+///
+/// Only the subset needed by the CLI output is supported: field and index selectors, wildcards,
+/// string literals and ranges. Missing fields are printed as empty values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Path(Path),
+    Range(Path, Vec<Segment>),
+}
+
+/// Parsed JSONPath expression such as `.nodes[*].hostname`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Path {
+    from_root: bool,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Field(String),
+    Index(i64),
+    Wildcard,
+}
+
+impl Template {
+    pub(crate) fn parse(template: &str) -> Result<Template, String> {
+        let mut stack: Vec<(Path, Vec<Segment>)> = Vec::new();
+        let mut segments = Vec::new();
+        let mut rest = template;
+
+        while !rest.is_empty() {
+            let start = match rest.find('{') {
+                Some(start) => start,
+                None => {
+                    segments.push(Segment::Text(rest.to_string()));
+                    break;
+                }
+            };
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+
+            let end = match find_unquoted(&rest[start..], '}') {
+                Some(end) => start + end,
+                None => return Err(format!("unclosed expression in \"{}\"", template)),
+            };
+            let expression = rest[start + 1..end].trim();
+            rest = &rest[end + 1..];
+
+            if let Some(range) = expression.strip_prefix("range ") {
+                stack.push((Path::parse(range.trim())?, segments));
+                segments = Vec::new();
+            } else if expression == "end" {
+                match stack.pop() {
+                    Some((path, outer)) => {
+                        let inner = std::mem::replace(&mut segments, outer);
+                        segments.push(Segment::Range(path, inner));
+                    }
+                    None => return Err(format!("unexpected {{end}} in \"{}\"", template)),
+                }
+            } else if let Some(literal) = string_literal(expression) {
+                segments.push(Segment::Text(literal));
+            } else {
+                segments.push(Segment::Path(Path::parse(expression)?));
+            }
+        }
+
+        if !stack.is_empty() {
+            return Err(format!("range without {{end}} in \"{}\"", template));
+        }
+
+        Ok(Template { segments })
+    }
+
+    /// Evaluate the template over the JSON value.
+    pub(crate) fn render(&self, root: &Value) -> String {
+        let mut rendered = String::new();
+        render_segments(&self.segments, root, root, &mut rendered);
+
+        rendered
+    }
+}
+
+impl Path {
+    /// Parse a path, it's relative to the current value unless it starts with `$`.
+    pub(crate) fn parse(path: &str) -> Result<Path, String> {
+        let invalid = || format!("invalid JSONPath expression \"{}\"", path);
+
+        let (from_root, rest) = match path.strip_prefix('$') {
+            Some(rest) => (true, rest),
+            None => (false, path.strip_prefix('@').unwrap_or(path)),
+        };
+        // Allow fields without the leading dot like "status" in custom columns.
+        let normalized = match rest {
+            "" | "." => String::new(),
+            _ if rest.starts_with(['.', '[']) => rest.to_string(),
+            _ => format!(".{}", rest),
+        };
+        let mut rest = normalized.as_str();
+
+        let mut steps = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                if after.starts_with('.') {
+                    return Err(format!(
+                        "recursive descent is not supported in \"{}\"",
+                        path
+                    ));
+                }
+                if let Some(after) = after.strip_prefix('*') {
+                    steps.push(Step::Wildcard);
+                    rest = after;
+                    continue;
+                }
+                let len = after.find(['.', '[']).unwrap_or(after.len());
+                if len == 0 {
+                    return Err(invalid());
+                }
+                steps.push(Step::Field(after[..len].to_string()));
+                rest = &after[len..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = find_unquoted(after, ']').ok_or_else(invalid)?;
+                let selector = after[..end].trim();
+                rest = &after[end + 1..];
+
+                if selector.starts_with('?') {
+                    return Err(format!("filters are not supported in \"{}\"", path));
+                } else if selector == "*" {
+                    steps.push(Step::Wildcard);
+                } else if let Some(field) = string_literal(selector) {
+                    steps.push(Step::Field(field));
+                } else {
+                    steps.push(Step::Index(selector.parse().map_err(|_| invalid())?));
+                }
+            } else {
+                return Err(invalid());
+            }
+        }
+
+        Ok(Path { from_root, steps })
+    }
+
+    /// Get all values selected by the path.
+    pub(crate) fn select<'a>(&self, root: &'a Value, current: &'a Value) -> Vec<&'a Value> {
+        let start = if self.from_root { root } else { current };
+        let mut values = vec![start];
+
+        for step in self.steps.iter() {
+            values = values
+                .into_iter()
+                .flat_map(|value| match (step, value) {
+                    (Step::Field(name), Value::Object(object)) => {
+                        object.get(name).into_iter().collect()
+                    }
+                    (Step::Index(index), Value::Array(items)) => {
+                        let index = if *index < 0 {
+                            items.len() as i64 + index
+                        } else {
+                            *index
+                        };
+                        usize::try_from(index)
+                            .ok()
+                            .and_then(|index| items.get(index))
+                            .into_iter()
+                            .collect()
+                    }
+                    (Step::Wildcard, Value::Array(items)) => items.iter().collect(),
+                    (Step::Wildcard, Value::Object(object)) => object.values().collect(),
+                    _ => Vec::new(),
+                })
+                .collect();
+        }
+
+        values
+    }
+}
+
+/// Print a selected value the way kubectl does: strings without quotes and objects as JSON.
+pub(crate) fn format_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn render_segments(segments: &[Segment], root: &Value, current: &Value, rendered: &mut String) {
+    for segment in segments.iter() {
+        match segment {
+            Segment::Text(text) => rendered.push_str(text),
+            Segment::Path(path) => {
+                let values: Vec<String> = path
+                    .select(root, current)
+                    .into_iter()
+                    .map(format_value)
+                    .collect();
+                rendered.push_str(&values.join(" "));
+            }
+            Segment::Range(path, inner) => {
+                for value in path.select(root, current) {
+                    render_segments(inner, root, value, rendered);
+                }
+            }
+        }
+    }
+}
+
+/// Find the character outside of quoted string literals, e.g. `}` in `{"}"}`.
+fn find_unquoted(s: &str, target: char) -> Option<usize> {
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == target => return Some(i),
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None => {}
+        }
+    }
+
+    None
+}
+
+/// Parse a quoted string literal like `"\n"` or `'name'`.
+fn string_literal(expression: &str) -> Option<String> {
+    let quote = expression.chars().next()?;
+    if (quote != '"' && quote != '\'') || expression.len() < 2 || !expression.ends_with(quote) {
+        return None;
+    }
+
+    let mut literal = String::new();
+    let mut chars = expression[1..expression.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            literal.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => literal.push('\n'),
+            Some('t') => literal.push('\t'),
+            Some('r') => literal.push('\r'),
+            Some(other) => literal.push(other),
+            None => literal.push('\\'),
+        }
+    }
+
+    Some(literal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(template: &str, value: &Value) -> String {
+        Template::parse(template).unwrap().render(value)
+    }
+
+    fn cluster() -> Value {
+        json!({
+            "id": "c1",
+            "labels": {"role": "web", "a.b/c": "d"},
+            "nodes": [
+                {"hostname": "n1", "id": "1"},
+                {"hostname": "n2", "id": "2"},
+                {"hostname": "n3", "id": "3"},
+            ],
+        })
+    }
+
+    #[test]
+    fn fields() {
+        assert_eq!(render("{.id}", &cluster()), "c1");
+        assert_eq!(render("id={.id};", &cluster()), "id=c1;");
+        assert_eq!(render("{.labels.role}", &cluster()), "web");
+        assert_eq!(render("{.labels['a.b/c']}", &cluster()), "d");
+        assert_eq!(render("{.missing}", &cluster()), "");
+        assert_eq!(
+            render("{.labels}", &json!({"labels": {"a": 1}})),
+            r#"{"a":1}"#
+        );
+    }
+
+    #[test]
+    fn indices() {
+        assert_eq!(render("{.nodes[0].hostname}", &cluster()), "n1");
+        assert_eq!(render("{.nodes[2].hostname}", &cluster()), "n3");
+        assert_eq!(render("{.nodes[-1].hostname}", &cluster()), "n3");
+        assert_eq!(render("{.nodes[-3].hostname}", &cluster()), "n1");
+        assert_eq!(render("{.nodes[3].hostname}", &cluster()), "");
+        assert_eq!(render("{.nodes[-4].hostname}", &cluster()), "");
+    }
+
+    #[test]
+    fn wildcards() {
+        assert_eq!(render("{.nodes[*].hostname}", &cluster()), "n1 n2 n3");
+        assert_eq!(render("{.nodes.*.id}", &cluster()), "1 2 3");
+        assert_eq!(
+            render("{.labels.*}", &json!({"labels": {"a": "1", "b": "2"}})),
+            "1 2"
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(
+            render(
+                r#"{range .nodes[*]}{.hostname}={.id}{"\n"}{end}"#,
+                &cluster()
+            ),
+            "n1=1\nn2=2\nn3=3\n"
+        );
+        assert_eq!(
+            render(
+                "{range .items[*]}{.id}:{range .nodes[*]}{.id},{end};{end}",
+                &json!({"items": [{"id": "a", "nodes": [{"id": 1}, {"id": 2}]}, {"id": "b"}]})
+            ),
+            "a:1,2,;b:;"
+        );
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(render(r#"{"\t"}{.id}{'\n'}"#, &cluster()), "\tc1\n");
+        assert_eq!(render(r#"{"}"}{.id}{"{"}"#, &cluster()), "}c1{");
+        assert_eq!(render(r#"{"\"}"}"#, &cluster()), "\"}");
+        assert_eq!(render("{'{.id}'}", &cluster()), "{.id}");
+        assert_eq!(
+            render("{.labels['x]y']}", &json!({"labels": {"x]y": "z"}})),
+            "z"
+        );
+    }
+
+    #[test]
+    fn roots() {
+        let value = json!({"id": "c1", "items": [{"id": "a"}, {"id": "b"}]});
+        assert_eq!(render("{range .items[*]}{.id}{end}", &value), "ab");
+        assert_eq!(render("{range .items[*]}{@.id}{end}", &value), "ab");
+        assert_eq!(render("{range .items[*]}{$.id}{end}", &value), "c1c1");
+        assert_eq!(render("{$.items[1].id}", &value), "b");
+        assert_eq!(render("{@}", &json!("x")), "x");
+    }
+
+    #[test]
+    fn errors() {
+        for template in [
+            "{.id",
+            "{end}",
+            "{range .nodes[*]}{.id}",
+            "{..id}",
+            "{.nodes[?(@.id)]}",
+            "{.nodes[x]}",
+            "{.nodes[0}",
+            "{.id.}",
+            r#"{"}"#,
+        ] {
+            assert!(
+                Template::parse(template).is_err(),
+                "{} must be rejected",
+                template
+            );
+        }
+    }
+}
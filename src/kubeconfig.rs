@@ -0,0 +1,369 @@
+use anyhow::{bail, Context, Result};
+use serde_yaml::{Mapping, Value};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Where to put a downloaded cluster kubeconfig.
+#[derive(Debug)]
+pub(crate) enum Target {
+    /// Print kubeconfig to stdout.
+    Stdout,
+
+    /// Write kubeconfig to the file.
+    File(PathBuf),
+
+    /// Merge kubeconfig into the kubeconfig file with the context name, cluster name is used
+    /// as the context name if it's not provided.
+    Merge {
+        path: PathBuf,
+        context: Option<String>,
+        use_context: bool,
+    },
+}
+
+/// Get the kubeconfig path used by kubectl: the first file from KUBECONFIG or `~/.kube/config`.
+pub(crate) fn default_path() -> Result<PathBuf> {
+    if let Some(paths) = env::var_os("KUBECONFIG") {
+        if let Some(path) = env::split_paths(&paths).find(|path| !path.as_os_str().is_empty()) {
+            return Ok(path);
+        }
+    }
+
+    let home = dirs::home_dir().context("Failed to find user home directory")?;
+
+    Ok(home.join(".kube").join("config"))
+}
+
+/// Write kubeconfig into the file, it's only readable by the current user since it contains
+/// credentials.
+pub(crate) fn write(path: &Path, kubeconfig: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        if !dir.as_os_str().is_empty() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+        }
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to open kubeconfig file {}", path.display()))?;
+    file.write_all(kubeconfig.as_bytes())
+        .with_context(|| format!("Failed to write kubeconfig file {}", path.display()))?;
+
+    Ok(())
+}
+
+/// Merge cluster kubeconfig into the kubeconfig file.
+///
+/// Cluster, user and context of the cluster kubeconfig are renamed to the context name and replace
+/// existing entries with the same name.
+pub(crate) fn merge(path: &Path, kubeconfig: &str, context: &str, use_context: bool) -> Result<()> {
+    let config: Value = if path.exists() {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read kubeconfig file {}", path.display()))?;
+        if raw.trim().is_empty() {
+            empty()
+        } else {
+            serde_yaml::from_str(&raw)
+                .with_context(|| format!("Failed to parse kubeconfig file {}", path.display()))?
+        }
+    } else {
+        empty()
+    };
+    let config = merge_config(config, kubeconfig, context, use_context)?;
+
+    let serialized = serde_yaml::to_string(&config).context("Failed to serialize kubeconfig")?;
+
+    write(path, &serialized)
+}
+
+/// Merge cluster kubeconfig into the parsed kubeconfig file, the cluster context becomes current
+/// if `use_context` is set or there is no current context yet.
+fn merge_config(
+    mut config: Value,
+    kubeconfig: &str,
+    context: &str,
+    use_context: bool,
+) -> Result<Value> {
+    let cluster_config: Value =
+        serde_yaml::from_str(kubeconfig).context("Failed to parse cluster kubeconfig")?;
+
+    // Use the current context of the cluster kubeconfig or its first context.
+    let cluster_context = match cluster_config
+        .get("current-context")
+        .and_then(Value::as_str)
+    {
+        Some(current) => named(&cluster_config, "contexts", current),
+        None => cluster_config
+            .get("contexts")
+            .and_then(Value::as_sequence)
+            .and_then(|contexts| contexts.first()),
+    };
+    let cluster_context = match cluster_context.and_then(|context| context.get("context")) {
+        Some(cluster_context) => cluster_context,
+        None => bail!("Cluster kubeconfig doesn't contain any context"),
+    };
+
+    let cluster_name = field(cluster_context, "cluster")?;
+    let user_name = field(cluster_context, "user")?;
+
+    let mut cluster = match named(&cluster_config, "clusters", cluster_name) {
+        Some(cluster) => cluster.clone(),
+        None => bail!(
+            "Cluster kubeconfig doesn't contain cluster {}",
+            cluster_name
+        ),
+    };
+    let mut user = match named(&cluster_config, "users", user_name) {
+        Some(user) => user.clone(),
+        None => bail!("Cluster kubeconfig doesn't contain user {}", user_name),
+    };
+    let mut new_context = cluster_context.clone();
+
+    set(&mut cluster, "name", context);
+    set(&mut user, "name", context);
+    set(&mut new_context, "cluster", context);
+    set(&mut new_context, "user", context);
+    let mut context_entry = Mapping::new();
+    context_entry.insert(Value::from("name"), Value::from(context));
+    context_entry.insert(Value::from("context"), new_context);
+
+    upsert(&mut config, "clusters", cluster)?;
+    upsert(&mut config, "users", user)?;
+    upsert(&mut config, "contexts", Value::Mapping(context_entry))?;
+
+    let current = config
+        .get("current-context")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if use_context || current.is_empty() {
+        set(&mut config, "current-context", context);
+    }
+
+    Ok(config)
+}
+
+fn empty() -> Value {
+    let mut config = Mapping::new();
+    config.insert(Value::from("apiVersion"), Value::from("v1"));
+    config.insert(Value::from("kind"), Value::from("Config"));
+    config.insert(Value::from("clusters"), Value::Sequence(Vec::new()));
+    config.insert(Value::from("users"), Value::Sequence(Vec::new()));
+    config.insert(Value::from("contexts"), Value::Sequence(Vec::new()));
+    config.insert(Value::from("current-context"), Value::from(""));
+    config.insert(Value::from("preferences"), Value::Mapping(Mapping::new()));
+
+    Value::Mapping(config)
+}
+
+fn named<'a>(config: &'a Value, list: &str, name: &str) -> Option<&'a Value> {
+    config
+        .get(list)
+        .and_then(Value::as_sequence)
+        .and_then(|entries| {
+            entries
+                .iter()
+                .find(|entry| entry.get("name").and_then(Value::as_str) == Some(name))
+        })
+}
+
+fn field<'a>(value: &'a Value, key: &str) -> Result<&'a str> {
+    match value.get(key).and_then(Value::as_str) {
+        Some(field) => Ok(field),
+        None => bail!("Cluster kubeconfig context doesn't contain {}", key),
+    }
+}
+
+fn set(value: &mut Value, key: &str, field: &str) {
+    if let Some(mapping) = value.as_mapping_mut() {
+        mapping.insert(Value::from(key), Value::from(field));
+    }
+}
+
+fn upsert(config: &mut Value, list: &str, entry: Value) -> Result<()> {
+    let mapping = match config.as_mapping_mut() {
+        Some(mapping) => mapping,
+        None => bail!("Kubeconfig file is not a mapping"),
+    };
+
+    let key = Value::from(list);
+    if !matches!(mapping.get(&key), Some(Value::Sequence(_))) {
+        mapping.insert(key.clone(), Value::Sequence(Vec::new()));
+    }
+
+    if let Some(Value::Sequence(entries)) = mapping.get_mut(&key) {
+        let name = entry.get("name").cloned();
+        match entries
+            .iter_mut()
+            .find(|existing| existing.get("name").cloned() == name)
+        {
+            Some(existing) => *existing = entry,
+            None => entries.push(entry),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLUSTER_KUBECONFIG: &str = "
+apiVersion: v1
+kind: Config
+clusters:
+- name: kubernetes
+  cluster:
+    server: https://10.0.0.1:6443
+users:
+- name: admin
+  user:
+    token: secret
+contexts:
+- name: admin@kubernetes
+  context:
+    cluster: kubernetes
+    user: admin
+current-context: admin@kubernetes
+";
+
+    fn merge(config: &str, context: &str, use_context: bool) -> Value {
+        let config = serde_yaml::from_str(config).unwrap();
+        merge_config(config, CLUSTER_KUBECONFIG, context, use_context).unwrap()
+    }
+
+    fn names(config: &Value, list: &str) -> Vec<String> {
+        config[list]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    fn current(config: &Value) -> &str {
+        config["current-context"].as_str().unwrap()
+    }
+
+    #[test]
+    fn merge_into_empty() {
+        let config = merge_config(empty(), CLUSTER_KUBECONFIG, "prod", false).unwrap();
+
+        assert_eq!(names(&config, "clusters"), vec!["prod"]);
+        assert_eq!(names(&config, "users"), vec!["prod"]);
+        assert_eq!(names(&config, "contexts"), vec!["prod"]);
+        assert_eq!(current(&config), "prod");
+        assert_eq!(
+            config["clusters"][0]["cluster"]["server"].as_str(),
+            Some("https://10.0.0.1:6443")
+        );
+        assert_eq!(
+            config["contexts"][0]["context"]["cluster"].as_str(),
+            Some("prod")
+        );
+        assert_eq!(
+            config["contexts"][0]["context"]["user"].as_str(),
+            Some("prod")
+        );
+    }
+
+    #[test]
+    fn merge_replaces_entries() {
+        let config = merge(
+            "
+clusters:
+- name: prod
+  cluster:
+    server: https://old:6443
+users:
+- name: prod
+  user:
+    token: old
+contexts:
+- name: prod
+  context:
+    cluster: prod
+    user: prod
+    namespace: old
+",
+            "prod",
+            false,
+        );
+
+        assert_eq!(names(&config, "clusters"), vec!["prod"]);
+        assert_eq!(names(&config, "users"), vec!["prod"]);
+        assert_eq!(names(&config, "contexts"), vec!["prod"]);
+        assert_eq!(
+            config["clusters"][0]["cluster"]["server"].as_str(),
+            Some("https://10.0.0.1:6443")
+        );
+        assert_eq!(config["users"][0]["user"]["token"].as_str(), Some("secret"));
+        assert!(config["contexts"][0]["context"].get("namespace").is_none());
+    }
+
+    #[test]
+    fn merge_keeps_unrelated() {
+        let config = merge(
+            "
+apiVersion: v1
+kind: Config
+preferences:
+  colors: true
+clusters:
+- name: dev
+  cluster:
+    server: https://dev:6443
+users:
+- name: dev
+  user:
+    token: dev
+contexts:
+- name: dev
+  context:
+    cluster: dev
+    user: dev
+current-context: dev
+",
+            "prod",
+            false,
+        );
+
+        assert_eq!(names(&config, "clusters"), vec!["dev", "prod"]);
+        assert_eq!(names(&config, "users"), vec!["dev", "prod"]);
+        assert_eq!(names(&config, "contexts"), vec!["dev", "prod"]);
+        assert_eq!(config["preferences"]["colors"].as_bool(), Some(true));
+        assert_eq!(config["kind"].as_str(), Some("Config"));
+        assert_eq!(current(&config), "dev");
+    }
+
+    #[test]
+    fn merge_use_context() {
+        let existing = "contexts: []\ncurrent-context: dev\n";
+        assert_eq!(current(&merge(existing, "prod", false)), "dev");
+        assert_eq!(current(&merge(existing, "prod", true)), "prod");
+
+        let without_current = "contexts: []\n";
+        assert_eq!(current(&merge(without_current, "prod", false)), "prod");
+        assert_eq!(current(&merge(without_current, "prod", true)), "prod");
+    }
+
+    #[test]
+    fn merge_invalid() {
+        let config = serde_yaml::from_str("[]").unwrap();
+        assert!(merge_config(config, CLUSTER_KUBECONFIG, "prod", false).is_err());
+
+        let kubeconfig = "contexts: []\n";
+        assert!(merge_config(empty(), kubeconfig, "prod", false).is_err());
+    }
+}
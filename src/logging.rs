@@ -0,0 +1,128 @@
+use log::{debug, info, log_enabled, Level, LevelFilter};
+use serde::Serialize;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+
+// Placeholder used instead of the MKS token in every debug message.
+const REDACTED: &str = "<redacted>";
+
+static TOKENS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Initialize stderr logging according to the global debug and verbose flags.
+///
+/// Verbose mode logs every MKS API operation with its resource identifiers and timing,
+/// debug mode additionally dumps request and response bodies.
+pub(crate) fn init(debug: bool, verbose: bool) {
+    let level = if debug {
+        LevelFilter::Debug
+    } else if verbose {
+        LevelFilter::Info
+    } else {
+        LevelFilter::Warn
+    };
+
+    env_logger::Builder::new()
+        .filter_level(LevelFilter::Warn)
+        .filter_module(module_path!().split("::").next().unwrap_or_default(), level)
+        .format(|buf, record| {
+            writeln!(
+                buf,
+                "[{}] {}",
+                record.level().to_string().to_lowercase(),
+                record.args()
+            )
+        })
+        .init();
+}
+
+/// Remember the MKS token so it is never printed in debug output.
+pub(crate) fn set_token(token: &str) {
+    if !token.is_empty() {
+        if let Ok(mut tokens) = TOKENS.lock() {
+            tokens.push(String::from(token));
+        }
+    }
+
+    debug!("Using MKS token {}", REDACTED);
+}
+
+/// Replace MKS tokens with a placeholder.
+pub(crate) fn redact(message: &str) -> String {
+    let mut message = String::from(message);
+    if let Ok(tokens) = TOKENS.lock() {
+        for token in tokens.iter() {
+            message = message.replace(token.as_str(), REDACTED);
+        }
+    }
+
+    message
+}
+
+/// Dump a serializable body in debug mode.
+pub(crate) fn dump<T: Serialize>(label: &str, data: &T) {
+    if !log_enabled!(Level::Debug) {
+        return;
+    }
+
+    match serde_json::to_string_pretty(data) {
+        Ok(serialized) => debug!("{}:\n{}", label, redact(&serialized)),
+        Err(err) => debug!("{}: failed to serialize: {}", label, err),
+    }
+}
+
+/// Run a single MKS API operation logging its name, resource identifiers, duration and result.
+pub(crate) fn call<T, F>(operation: &str, ids: &str, f: F) -> Result<T, selectel_mks::error::Error>
+where
+    T: Serialize,
+    F: FnOnce() -> Result<T, selectel_mks::error::Error>,
+{
+    run(operation, ids, f, |response| dump("response", response))
+}
+
+/// Run a single MKS API operation like `call` without dumping its response that contains secrets,
+/// e.g. kubeconfig certificates and keys.
+pub(crate) fn call_secret<T, F>(
+    operation: &str,
+    ids: &str,
+    f: F,
+) -> Result<T, selectel_mks::error::Error>
+where
+    F: FnOnce() -> Result<T, selectel_mks::error::Error>,
+{
+    run(operation, ids, f, |_| debug!("response: {}", REDACTED))
+}
+
+fn run<T, F, D>(
+    operation: &str,
+    ids: &str,
+    f: F,
+    dump_response: D,
+) -> Result<T, selectel_mks::error::Error>
+where
+    F: FnOnce() -> Result<T, selectel_mks::error::Error>,
+    D: FnOnce(&T),
+{
+    if ids.is_empty() {
+        info!("{}", operation);
+    } else {
+        info!("{} {}", operation, ids);
+    }
+
+    let started = Instant::now();
+    let result = f();
+    let elapsed = started.elapsed();
+
+    match &result {
+        Ok(response) => {
+            info!("{} succeeded in {} ms", operation, elapsed.as_millis());
+            dump_response(response);
+        }
+        Err(err) => {
+            info!("{} failed in {} ms", operation, elapsed.as_millis());
+            debug!("{} error: {}", operation, redact(&err.to_string()));
+        }
+    }
+
+    result
+}
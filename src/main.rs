@@ -1,41 +1,167 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use selectel_mks::cluster as mks_cluster;
+use selectel_mks::kubeversion as mks_kubeversion;
+use selectel_mks::node as mks_node;
 use selectel_mks::nodegroup as mks_nodegroup;
+use selectel_mks::task as mks_task;
 use selectel_mks::Client;
+use std::path::PathBuf;
 use structopt::StructOpt;
 
+mod api;
 mod conf;
 
 mod cluster;
+mod completion;
+mod confirm;
+mod jsonpath;
+mod kubeconfig;
 mod kubeversion;
+mod manifest;
 mod node;
 mod nodegroup;
+mod output;
+mod plan;
+mod profile;
+mod region;
+mod resolve;
 mod task;
+mod wait;
 
 pub(crate) mod json;
+pub(crate) mod logging;
+pub(crate) mod yaml;
 
-fn main() -> Result<()> {
+// Exit code of commands that reached their --timeout, other errors exit with 1.
+const TIMEOUT_EXIT_CODE: i32 = 2;
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("Error: {:?}", err);
+
+        let code = if err.is::<wait::TimeoutError>() {
+            TIMEOUT_EXIT_CODE
+        } else {
+            1
+        };
+        std::process::exit(code);
+    }
+}
+
+fn run() -> Result<()> {
     let cli_opts = conf::CliOptions::from_args();
 
-    let client = Client::new(&cli_opts.mks_endpoint, &cli_opts.mks_token)
+    logging::init(cli_opts.debug, cli_opts.verbose);
+
+    let config_path = profile::config_path(cli_opts.config.as_deref())?;
+
+    // Completion scripts are generated from the options.
+    if let conf::Resource::Completion(conf::Completion { shell }) = cli_opts.resource {
+        completion::print_script(shell)?;
+
+        return Ok(());
+    }
+
+    // Configuration commands don't need an MKS client.
+    if let conf::Resource::Config(conf::Config { command }) = cli_opts.resource {
+        match command {
+            // config add
+            conf::ConfigCommand::Add {
+                name,
+                endpoint,
+                token,
+                token_command,
+                set_current,
+            } => profile::add(
+                &config_path,
+                &name,
+                &endpoint,
+                token,
+                token_command,
+                set_current,
+            )?,
+
+            // config list
+            conf::ConfigCommand::List {} => profile::list(&config_path)?,
+
+            // config remove
+            conf::ConfigCommand::Remove { name } => profile::remove(&config_path, &name)?,
+
+            // config use
+            conf::ConfigCommand::Use { name } => profile::use_profile(&config_path, &name)?,
+        };
+
+        return Ok(());
+    }
+
+    let credentials = profile::credentials(
+        &config_path,
+        cli_opts.profile.as_deref(),
+        cli_opts.mks_endpoint,
+        cli_opts.region,
+        cli_opts.mks_token,
+    )?;
+    logging::set_token(&credentials.token);
+
+    let client = Client::new(&credentials.endpoint, &credentials.token)
         .context("Failed to initialize MKS client")?;
+    // Client for API methods that are not supported by the MKS client library.
+    let api_client = api::Client::new(&credentials.endpoint, &credentials.token)
+        .context("Failed to initialize MKS API client")?;
+
+    let output = cli_opts.output.clone().unwrap_or_default();
 
     match cli_opts.resource {
+        // apply
+        conf::Resource::Apply(conf::Apply {
+            filename,
+            prune,
+            yes,
+            timeout,
+            poll_interval,
+        }) => plan::apply(
+            &client,
+            &api_client,
+            &filename,
+            credentials.region.as_deref(),
+            prune,
+            yes,
+            &wait::WaitOpts::new(timeout, poll_interval),
+        )?,
+
+        // diff
+        conf::Resource::Diff(conf::Diff { filename, prune }) => plan::diff(
+            &client,
+            &output,
+            &filename,
+            credentials.region.as_deref(),
+            prune,
+        )?,
+
         // cluster get
         conf::Resource::Cluster(conf::Cluster {
-            command: conf::ClusterCommand::Get { output, cluster_id },
-        }) => cluster::get(&client, &output, &cluster_id)?,
+            command: conf::ClusterCommand::Get { cluster_id },
+        }) => cluster::get(&client, &output, &resolve::cluster(&client, &cluster_id)?)?,
 
         // cluster list
         conf::Resource::Cluster(conf::Cluster {
-            command: conf::ClusterCommand::List { output },
-        }) => cluster::list(&client, &output)?,
+            command:
+                conf::ClusterCommand::List {
+                    list_opts,
+                    filter,
+                    sort_by,
+                },
+        }) => {
+            let table_opts = list_opts.into();
+            output::check_list::<mks_cluster::schemas::Cluster>(&output, &table_opts)?;
+
+            cluster::list(&client, &output, &table_opts, &filter, sort_by)?
+        }
 
         // cluster create
         conf::Resource::Cluster(conf::Cluster {
             command:
                 conf::ClusterCommand::Create {
-                    output,
                     name,
                     kube_version,
                     region,
@@ -45,8 +171,20 @@ fn main() -> Result<()> {
                     enable_autorepair,
                     enable_patch_version_auto_upgrade,
                     zonal,
+                    wait_args,
                 },
         }) => {
+            let region = match region {
+                Some(region) => {
+                    region::check(&region, credentials.region.as_deref())?;
+                    region
+                }
+                None => match credentials.region {
+                    Some(region) => region,
+                    None => bail!("Cluster region is not set, use --region"),
+                },
+            };
+
             let mut opts = mks_cluster::schemas::CreateOpts::new(&name, &kube_version, &region);
             if let Some(network_id) = network_id {
                 opts = opts.with_network_id(&network_id);
@@ -68,29 +206,197 @@ fn main() -> Result<()> {
                 opts = opts.with_zonal(zonal);
             }
 
-            cluster::create(&client, &output, opts)?
+            cluster::create(
+                &client,
+                &output,
+                opts,
+                wait::WaitOpts::from_args(&wait_args),
+            )?
+        }
+
+        // cluster set
+        conf::Resource::Cluster(conf::Cluster {
+            command:
+                conf::ClusterCommand::Set {
+                    cluster_id,
+                    maintenance_window_start,
+                    enable_autorepair,
+                    enable_patch_version_auto_upgrade,
+                },
+        }) => cluster::set(
+            &client,
+            &api_client,
+            &output,
+            &resolve::cluster(&client, &cluster_id)?,
+            api::UpdateClusterOpts {
+                maintenance_window_start,
+                enable_autorepair,
+                enable_patch_version_auto_upgrade,
+            },
+        )?,
+
+        // cluster kubeconfig
+        conf::Resource::Cluster(conf::Cluster {
+            command:
+                conf::ClusterCommand::Kubeconfig {
+                    cluster_id,
+                    file,
+                    merge,
+                    kubeconfig,
+                    context,
+                    use_context,
+                },
+        }) => {
+            let target = if merge {
+                let path = match kubeconfig {
+                    Some(path) => PathBuf::from(path),
+                    None => kubeconfig::default_path()?,
+                };
+                kubeconfig::Target::Merge {
+                    path,
+                    context,
+                    use_context,
+                }
+            } else if let Some(file) = file {
+                kubeconfig::Target::File(PathBuf::from(file))
+            } else {
+                kubeconfig::Target::Stdout
+            };
+
+            cluster::kubeconfig(
+                &client,
+                &api_client,
+                &resolve::cluster(&client, &cluster_id)?,
+                target,
+            )?
+        }
+
+        // cluster clone
+        conf::Resource::Cluster(conf::Cluster {
+            command:
+                conf::ClusterCommand::Clone {
+                    cluster_id,
+                    name,
+                    region,
+                    target_profile,
+                    kube_version,
+                    network_id,
+                    subnet_id,
+                    timeout,
+                    poll_interval,
+                },
+        }) => {
+            let target_credentials = if region.is_some() || target_profile.is_some() {
+                profile::target_credentials(
+                    &config_path,
+                    &credentials,
+                    target_profile.as_deref(),
+                    region.clone(),
+                )?
+            } else {
+                credentials
+            };
+            logging::set_token(&target_credentials.token);
+            let target_client =
+                Client::new(&target_credentials.endpoint, &target_credentials.token)
+                    .context("Failed to initialize MKS client for the new cluster")?;
+            let target_api_client =
+                api::Client::new(&target_credentials.endpoint, &target_credentials.token)
+                    .context("Failed to initialize MKS API client for the new cluster")?;
+
+            let clone_opts = cluster::CloneOpts {
+                name,
+                region: region.or(target_credentials.region),
+                kube_version,
+                network_id,
+                subnet_id,
+            };
+
+            cluster::clone(
+                &client,
+                &api_client,
+                cluster::CloneTarget {
+                    client: &target_client,
+                    api_client: &target_api_client,
+                },
+                &output,
+                &resolve::cluster(&client, &cluster_id)?,
+                clone_opts,
+                &wait::WaitOpts::new(timeout, poll_interval),
+            )?
+        }
+
+        // cluster export
+        conf::Resource::Cluster(conf::Cluster {
+            command: conf::ClusterCommand::Export { cluster_id },
+        }) => {
+            let format = cli_opts.output.unwrap_or(output::Format::Yaml);
+            cluster::check_export_format(&format)?;
+
+            cluster::export(&client, &format, &resolve::cluster(&client, &cluster_id)?)?
         }
 
         // cluster delete
         conf::Resource::Cluster(conf::Cluster {
-            command: conf::ClusterCommand::Delete { cluster_id },
-        }) => cluster::delete(&client, &cluster_id)?,
+            command:
+                conf::ClusterCommand::Delete {
+                    cluster_id,
+                    yes,
+                    wait_args,
+                },
+        }) => cluster::delete(
+            &client,
+            &output,
+            &resolve::cluster(&client, &cluster_id)?,
+            yes,
+            &wait_args,
+        )?,
 
         // kubeversion list
         conf::Resource::Kubeversion(conf::Kubeversion {
-            command: conf::KubeversionCommand::List { output },
-        }) => kubeversion::list(&client, &output)?,
+            command: conf::KubeversionCommand::List { list_opts },
+        }) => {
+            let table_opts = list_opts.into();
+            output::check_list::<mks_kubeversion::schemas::KubeVersion>(&output, &table_opts)?;
+
+            kubeversion::list(&client, &output, &table_opts)?
+        }
 
         // node get
         conf::Resource::Node(conf::Node {
             command:
                 conf::NodeCommand::Get {
-                    output,
                     cluster_id,
                     nodegroup_id,
                     node_id,
                 },
-        }) => node::get(&client, &output, &cluster_id, &nodegroup_id, &node_id)?,
+        }) => {
+            let cluster_id = resolve::cluster(&client, &cluster_id)?;
+            let node_id = resolve::node(&client, &cluster_id, &nodegroup_id, &node_id)?;
+
+            node::get(&client, &output, &cluster_id, &nodegroup_id, &node_id)?
+        }
+
+        // node list
+        conf::Resource::Node(conf::Node {
+            command:
+                conf::NodeCommand::List {
+                    list_opts,
+                    cluster_id,
+                    nodegroup_id,
+                },
+        }) => {
+            let table_opts = list_opts.into();
+            output::check_list::<mks_node::schemas::Node>(&output, &table_opts)?;
+
+            node::list(
+                &client,
+                &output,
+                &table_opts,
+                &resolve::cluster(&client, &cluster_id)?,
+                nodegroup_id.as_deref(),
+            )?
+        }
 
         // node reinstall
         conf::Resource::Node(conf::Node {
@@ -99,23 +405,57 @@ fn main() -> Result<()> {
                     cluster_id,
                     nodegroup_id,
                     node_id,
+                    wait_args,
                 },
-        }) => node::reinstall(&client, &cluster_id, &nodegroup_id, &node_id)?,
+        }) => {
+            let cluster_id = resolve::cluster(&client, &cluster_id)?;
+            let node_id = resolve::node(&client, &cluster_id, &nodegroup_id, &node_id)?;
+
+            node::reinstall(
+                &client,
+                &output,
+                &cluster_id,
+                &nodegroup_id,
+                &node_id,
+                &wait_args,
+            )?
+        }
 
         // nodegroup list
         conf::Resource::Nodegroup(conf::Nodegroup {
-            command: conf::NodegroupCommand::List { output, cluster_id },
-        }) => nodegroup::list(&client, &output, &cluster_id)?,
+            command:
+                conf::NodegroupCommand::List {
+                    list_opts,
+                    cluster_id,
+                },
+        }) => {
+            let table_opts = list_opts.into();
+            output::check_list::<mks_nodegroup::schemas::Nodegroup>(&output, &table_opts)?;
+
+            nodegroup::list(
+                &client,
+                &output,
+                &table_opts,
+                &resolve::cluster(&client, &cluster_id)?,
+            )?
+        }
 
         // nodegroup get
         conf::Resource::Nodegroup(conf::Nodegroup {
             command:
                 conf::NodegroupCommand::Get {
-                    output,
                     cluster_id,
                     nodegroup_id,
+                    show_nodes,
                 },
-        }) => nodegroup::get(&client, &output, &cluster_id, &nodegroup_id)?,
+        }) => nodegroup::get(
+            &client,
+            &api_client,
+            &output,
+            &resolve::cluster(&client, &cluster_id)?,
+            &nodegroup_id,
+            show_nodes,
+        )?,
 
         // nodegroup create
         conf::Resource::Nodegroup(conf::Nodegroup {
@@ -132,6 +472,9 @@ fn main() -> Result<()> {
                     keypair_name,
                     affinity_policy,
                     availability_zone,
+                    label,
+                    taint,
+                    wait_args,
                 },
         }) => {
             let mut opts = mks_nodegroup::schemas::CreateOpts::new(
@@ -160,8 +503,19 @@ fn main() -> Result<()> {
             if let Some(affinity_policy) = affinity_policy {
                 opts = opts.with_affinity_policy(&affinity_policy);
             }
+            if !label.is_empty() {
+                opts = opts.with_labels(nodegroup::labels_map(label));
+            }
 
-            nodegroup::create(&client, &cluster_id, opts)?
+            nodegroup::create(
+                &client,
+                &api_client,
+                &output,
+                &resolve::cluster(&client, &cluster_id)?,
+                opts,
+                taint,
+                &wait_args,
+            )?
         }
 
         // nodegroup set
@@ -171,8 +525,51 @@ fn main() -> Result<()> {
                     cluster_id,
                     nodegroup_id,
                     nodes_count,
+                    label,
+                    remove_label,
+                    taint,
+                    remove_taint,
+                    enable_autoscale,
+                    autoscale_min_nodes,
+                    autoscale_max_nodes,
+                    wait_args,
+                },
+        }) => nodegroup::set(
+            &client,
+            &api_client,
+            &output,
+            &resolve::cluster(&client, &cluster_id)?,
+            &nodegroup_id,
+            nodegroup::Update {
+                nodes_count,
+                labels: label,
+                remove_labels: remove_label,
+                taints: taint,
+                remove_taints: remove_taint,
+                enable_autoscale,
+                autoscale_min_nodes,
+                autoscale_max_nodes,
+            },
+            &wait_args,
+        )?,
+
+        // nodegroup reinstall
+        conf::Resource::Nodegroup(conf::Nodegroup {
+            command:
+                conf::NodegroupCommand::Reinstall {
+                    cluster_id,
+                    nodegroup_id,
+                    max_unavailable,
+                    timeout,
+                    poll_interval,
                 },
-        }) => nodegroup::set(&client, &cluster_id, &nodegroup_id, nodes_count)?,
+        }) => nodegroup::reinstall(
+            &client,
+            &resolve::cluster(&client, &cluster_id)?,
+            &nodegroup_id,
+            max_unavailable,
+            &wait::WaitOpts::new(timeout, poll_interval),
+        )?,
 
         // nodegroup delete
         conf::Resource::Nodegroup(conf::Nodegroup {
@@ -180,23 +577,77 @@ fn main() -> Result<()> {
                 conf::NodegroupCommand::Delete {
                     cluster_id,
                     nodegroup_id,
+                    yes,
+                    wait_args,
                 },
-        }) => nodegroup::delete(&client, &cluster_id, &nodegroup_id)?,
+        }) => nodegroup::delete(
+            &client,
+            &output,
+            &resolve::cluster(&client, &cluster_id)?,
+            &nodegroup_id,
+            yes,
+            &wait_args,
+        )?,
 
         // task get
         conf::Resource::Task(conf::Task {
             command:
                 conf::TaskCommand::Get {
-                    output,
                     cluster_id,
                     task_id,
                 },
-        }) => task::get(&client, &output, &cluster_id, &task_id)?,
+        }) => task::get(
+            &client,
+            &output,
+            &resolve::cluster(&client, &cluster_id)?,
+            &task_id,
+        )?,
 
         // task list
         conf::Resource::Task(conf::Task {
-            command: conf::TaskCommand::List { output, cluster_id },
-        }) => task::list(&client, &output, &cluster_id)?,
+            command:
+                conf::TaskCommand::List {
+                    list_opts,
+                    cluster_id,
+                },
+        }) => {
+            let table_opts = list_opts.into();
+            output::check_list::<mks_task::schemas::Task>(&output, &table_opts)?;
+
+            task::list(
+                &client,
+                &output,
+                &table_opts,
+                &resolve::cluster(&client, &cluster_id)?,
+            )?
+        }
+
+        // task watch
+        conf::Resource::Task(conf::Task {
+            command:
+                conf::TaskCommand::Watch {
+                    cluster_id,
+                    task_id,
+                    timeout,
+                    poll_interval,
+                },
+        }) => task::watch(
+            &client,
+            &resolve::cluster(&client, &cluster_id)?,
+            task_id.as_deref(),
+            &wait::WaitOpts::new(timeout, poll_interval),
+        )?,
+
+        // complete
+        conf::Resource::Complete(conf::Complete { kind, cluster_id }) => completion::complete(
+            &client,
+            &[&credentials.endpoint, &credentials.token],
+            &kind,
+            cluster_id.as_deref(),
+        )?,
+
+        // completion and config commands are handled above
+        conf::Resource::Completion(_) | conf::Resource::Config(_) => unreachable!(),
     };
 
     Ok(())
@@ -0,0 +1,285 @@
+use anyhow::{bail, Context, Result};
+use selectel_mks::cluster;
+use selectel_mks::nodegroup;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Cluster manifest with its nodegroups.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ClusterSpec {
+    /// Cluster name, it's used to find an existing cluster.
+    pub(crate) name: String,
+
+    pub(crate) kube_version: String,
+
+    /// Cluster region, defaults to the region of the MKS endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) region: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) network_id: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) subnet_id: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) maintenance_window_start: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) enable_autorepair: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) enable_patch_version_auto_upgrade: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) zonal: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) nodegroups: Vec<NodegroupSpec>,
+}
+
+/// Nodegroup manifest.
+///
+/// Nodegroups don't have names, so an existing nodegroup is found by its identifier if it's
+/// provided or by its availability zone and volume parameters otherwise.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct NodegroupSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<String>,
+
+    pub(crate) nodes_count: u32,
+
+    pub(crate) availability_zone: String,
+
+    #[serde(default)]
+    pub(crate) local_volume: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) flavor_id: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) cpus: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) ram_mb: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) volume_gb: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) volume_type: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) keypair_name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) affinity_policy: Option<String>,
+
+    /// Kubernetes labels of all nodes, they're left as is when not provided.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) labels: Option<BTreeMap<String, String>>,
+}
+
+/// Read a YAML or JSON manifest from the file, "-" means stdin, and check its values.
+pub(crate) fn load(path: &str) -> Result<ClusterSpec> {
+    let raw = if path == "-" {
+        let mut raw = String::new();
+        io::stdin()
+            .read_to_string(&mut raw)
+            .context("Failed to read manifest from stdin")?;
+        raw
+    } else {
+        fs::read_to_string(path).with_context(|| format!("Failed to read manifest {}", path))?
+    };
+
+    let is_json = Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension == "json");
+    let spec: ClusterSpec = if is_json {
+        serde_json::from_str(&raw).with_context(|| format!("Failed to parse manifest {}", path))?
+    } else {
+        serde_yaml::from_str(&raw).with_context(|| format!("Failed to parse manifest {}", path))?
+    };
+
+    if let Some(maintenance_window_start) = &spec.maintenance_window_start {
+        if let Err(err) = crate::cluster::check_maintenance_window(maintenance_window_start.clone())
+        {
+            bail!(
+                "Invalid manifest {}: maintenance_window_start: {}",
+                path,
+                err
+            );
+        }
+    }
+
+    Ok(spec)
+}
+
+impl ClusterSpec {
+    /// Build a manifest that can be used to recreate the existing cluster.
+    ///
+    /// Only fields accepted by the create requests are kept, nodegroups are described by their
+    /// flavors since CPU and RAM values are not returned by the MKS API.
+    pub(crate) fn from_existing(
+        cluster: &cluster::schemas::Cluster,
+        nodegroups: &[nodegroup::schemas::Nodegroup],
+    ) -> ClusterSpec {
+        ClusterSpec {
+            name: cluster.name.clone(),
+            kube_version: cluster.kube_version.clone(),
+            region: Some(cluster.region.clone()),
+            network_id: Some(cluster.network_id.clone()),
+            subnet_id: Some(cluster.subnet_id.clone()),
+            maintenance_window_start: cluster.maintenance_window_start.clone(),
+            enable_autorepair: Some(cluster.enable_autorepair),
+            enable_patch_version_auto_upgrade: Some(cluster.enable_patch_version_auto_upgrade),
+            zonal: Some(cluster.zonal),
+            nodegroups: nodegroups
+                .iter()
+                .map(NodegroupSpec::from_existing)
+                .collect(),
+        }
+    }
+
+    /// Get options to create the cluster with all of its nodegroups.
+    pub(crate) fn create_opts(&self, region: &str) -> cluster::schemas::CreateOpts {
+        let mut opts = cluster::schemas::CreateOpts::new(&self.name, &self.kube_version, region);
+        if let Some(network_id) = &self.network_id {
+            opts = opts.with_network_id(network_id);
+        }
+        if let Some(subnet_id) = &self.subnet_id {
+            opts = opts.with_subnet_id(subnet_id);
+        }
+        if let Some(maintenance_window_start) = &self.maintenance_window_start {
+            opts = opts.with_maintenance_window_start(maintenance_window_start);
+        }
+        if let Some(enable_autorepair) = self.enable_autorepair {
+            opts = opts.with_enable_autorepair(enable_autorepair);
+        }
+        if let Some(enable_patch_version_auto_upgrade) = self.enable_patch_version_auto_upgrade {
+            opts = opts.with_enable_patch_version_auto_upgrade(enable_patch_version_auto_upgrade);
+        }
+        if let Some(zonal) = self.zonal {
+            opts = opts.with_zonal(zonal);
+        }
+        if !self.nodegroups.is_empty() {
+            opts = opts.with_nodegroups(
+                self.nodegroups
+                    .iter()
+                    .map(NodegroupSpec::create_opts)
+                    .collect(),
+            );
+        }
+
+        opts
+    }
+}
+
+impl NodegroupSpec {
+    /// Build a manifest that can be used to recreate the existing nodegroup.
+    pub(crate) fn from_existing(nodegroup: &nodegroup::schemas::Nodegroup) -> NodegroupSpec {
+        let (volume_gb, volume_type) = if nodegroup.local_volume {
+            (None, None)
+        } else {
+            (
+                Some(nodegroup.volume_gb),
+                Some(nodegroup.volume_type.clone()),
+            )
+        };
+        let labels = if nodegroup.labels.is_empty() {
+            None
+        } else {
+            Some(
+                nodegroup
+                    .labels
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            )
+        };
+
+        NodegroupSpec {
+            id: None,
+            nodes_count: nodegroup.nodes.len() as u32,
+            availability_zone: nodegroup.availability_zone.clone(),
+            local_volume: nodegroup.local_volume,
+            flavor_id: Some(nodegroup.flavor_id.clone()),
+            cpus: None,
+            ram_mb: None,
+            volume_gb,
+            volume_type,
+            keypair_name: None,
+            affinity_policy: None,
+            labels,
+        }
+    }
+
+    /// Get options to create the nodegroup.
+    pub(crate) fn create_opts(&self) -> nodegroup::schemas::CreateOpts {
+        let mut opts = nodegroup::schemas::CreateOpts::new(
+            self.nodes_count,
+            self.local_volume,
+            &self.availability_zone,
+        );
+        if let Some(flavor_id) = &self.flavor_id {
+            opts = opts.with_flavor_id(flavor_id);
+        }
+        if let Some(cpus) = self.cpus {
+            opts = opts.with_cpus(cpus);
+        }
+        if let Some(ram_mb) = self.ram_mb {
+            opts = opts.with_ram_mb(ram_mb);
+        }
+        if let Some(volume_gb) = self.volume_gb {
+            opts = opts.with_volume_gb(volume_gb);
+        }
+        if let Some(volume_type) = &self.volume_type {
+            opts = opts.with_volume_type(volume_type);
+        }
+        if let Some(keypair_name) = &self.keypair_name {
+            opts = opts.with_keypair_name(keypair_name);
+        }
+        if let Some(affinity_policy) = &self.affinity_policy {
+            opts = opts.with_affinity_policy(affinity_policy);
+        }
+        if let Some(node_labels) = &self.labels {
+            opts = opts.with_labels(labels(node_labels));
+        }
+
+        opts
+    }
+
+    /// Check if the existing nodegroup is described by this manifest.
+    pub(crate) fn matches(&self, nodegroup: &nodegroup::schemas::Nodegroup) -> bool {
+        if let Some(id) = &self.id {
+            return *id == nodegroup.id;
+        }
+
+        self.availability_zone == nodegroup.availability_zone
+            && self.local_volume == nodegroup.local_volume
+            && self
+                .flavor_id
+                .as_ref()
+                .map_or(true, |flavor_id| *flavor_id == nodegroup.flavor_id)
+            && self
+                .volume_gb
+                .map_or(true, |volume_gb| volume_gb == nodegroup.volume_gb)
+            && self
+                .volume_type
+                .as_ref()
+                .map_or(true, |volume_type| *volume_type == nodegroup.volume_type)
+    }
+}
+
+/// Convert labels into the map used by the MKS API.
+pub(crate) fn labels(labels: &BTreeMap<String, String>) -> HashMap<String, String> {
+    labels
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
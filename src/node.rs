@@ -1,84 +1,162 @@
-use anyhow::{bail, Context, Result};
+use anyhow::{Context, Result};
 use selectel_mks::node;
+use selectel_mks::task::schemas::Type as TaskType;
 use selectel_mks::Client;
 use term_table::row::Row;
 use term_table::table_cell::TableCell;
 use term_table::{Table, TableStyle};
 
-use crate::json;
+use crate::conf;
+use crate::logging;
+use crate::output;
+use crate::task;
 
 pub(crate) fn get(
     client: &Client,
-    output: &str,
+    format: &output::Format,
     cluster_id: &str,
     nodegroup_id: &str,
     node_id: &str,
 ) -> Result<()> {
-    let node = client
-        .get_node(cluster_id, nodegroup_id, node_id)
-        .context("Failed to get node")?;
-
-    match output {
-        "table" => get_print_table(&node),
-        "json" => json::print_json(node)?,
-        _ => bail!("Unknown output format"),
-    };
+    let node = logging::call(
+        "get_node",
+        &format!(
+            "cluster_id={} nodegroup_id={} node_id={}",
+            cluster_id, nodegroup_id, node_id
+        ),
+        || client.get_node(cluster_id, nodegroup_id, node_id),
+    )
+    .context("Failed to get node")?;
 
-    Ok(())
+    output::print(format, &node)
 }
 
-fn get_print_table(node: &node::schemas::Node) {
-    let updated_at = match &node.updated_at {
-        Some(time) => time.to_rfc3339(),
-        None => String::new(),
+impl output::Render for node::schemas::Node {
+    fn print_table(&self) {
+        let updated_at = match &self.updated_at {
+            Some(time) => time.to_rfc3339(),
+            None => String::new(),
+        };
+
+        let mut table = Table::new();
+        table.style = TableStyle::simple();
+        table.separate_rows = false;
+
+        table.add_row(Row::new(vec![
+            TableCell::new("id"),
+            TableCell::new(&self.id),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("created_at"),
+            TableCell::new(self.created_at.to_rfc3339()),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("updated_at"),
+            TableCell::new(&updated_at),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("hostname"),
+            TableCell::new(&self.hostname),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("ip"),
+            TableCell::new(&self.ip),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("nodegroup_id"),
+            TableCell::new(&self.nodegroup_id),
+        ]));
+
+        println!("{}", table.render());
+    }
+
+    fn names(&self) -> Option<Vec<String>> {
+        Some(vec![format!("node/{}", self.id)])
+    }
+}
+
+pub(crate) fn list(
+    client: &Client,
+    format: &output::Format,
+    table_opts: &output::TableOpts,
+    cluster_id: &str,
+    nodegroup_id: Option<&str>,
+) -> Result<()> {
+    let nodegroups = match nodegroup_id {
+        Some(nodegroup_id) => vec![logging::call(
+            "get_nodegroup",
+            &format!("cluster_id={} nodegroup_id={}", cluster_id, nodegroup_id),
+            || client.get_nodegroup(cluster_id, nodegroup_id),
+        )
+        .context("Failed to get nodegroup")?],
+        None => logging::call(
+            "list_nodegroups",
+            &format!("cluster_id={}", cluster_id),
+            || client.list_nodegroups(cluster_id),
+        )
+        .context("Failed to list nodegroups")?,
     };
+    let nodes: Vec<node::schemas::Node> = nodegroups
+        .into_iter()
+        .flat_map(|nodegroup| nodegroup.nodes)
+        .collect();
+
+    output::print_list(format, table_opts, &nodes)
+}
+
+impl output::Item for node::schemas::Node {
+    const KIND: &'static str = "node";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
 
-    let mut table = Table::new();
-    table.style = TableStyle::simple();
-    table.separate_rows = false;
-
-    table.add_row(Row::new(vec![
-        TableCell::new("id"),
-        TableCell::new(&node.id),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("created_at"),
-        TableCell::new(&node.created_at.to_rfc3339()),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("updated_at"),
-        TableCell::new(&updated_at),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("hostname"),
-        TableCell::new(&node.hostname),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("ip"),
-        TableCell::new(&node.ip),
-    ]));
-
-    table.add_row(Row::new(vec![
-        TableCell::new("nodegroup_id"),
-        TableCell::new(&node.nodegroup_id),
-    ]));
-
-    println!("{}", table.render());
+    fn fields() -> Vec<output::Field<Self>> {
+        vec![
+            output::Field::new("id", |node| node.id.clone()),
+            output::Field::new("hostname", |node| node.hostname.clone()),
+            output::Field::new("ip", |node| node.ip.clone()),
+            output::Field::new("nodegroup_id", |node| node.nodegroup_id.clone()),
+            output::Field::new("created_at", |node| node.created_at.to_rfc3339()),
+            output::Field::new("updated_at", |node| {
+                node.updated_at
+                    .map(|time| time.to_rfc3339())
+                    .unwrap_or_default()
+            }),
+        ]
+    }
 }
 
 pub(crate) fn reinstall(
     client: &Client,
+    format: &output::Format,
     cluster_id: &str,
     nodegroup_id: &str,
     node_id: &str,
+    wait_args: &conf::WaitArgs,
 ) -> Result<()> {
-    client
-        .reinstall_node(cluster_id, nodegroup_id, node_id)
-        .context("Failed to reinstall node")?;
+    let tasks = task::track(
+        client,
+        cluster_id,
+        &TaskType::NodeReinstall,
+        wait_args,
+        || {
+            logging::call(
+                "reinstall_node",
+                &format!(
+                    "cluster_id={} nodegroup_id={} node_id={}",
+                    cluster_id, nodegroup_id, node_id
+                ),
+                || client.reinstall_node(cluster_id, nodegroup_id, node_id),
+            )
+            .context("Failed to reinstall node")
+        },
+    )?;
 
-    Ok(())
+    task::print_started(format, &tasks)
 }
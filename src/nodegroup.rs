@@ -1,169 +1,1110 @@
 use anyhow::{bail, Context, Result};
 use selectel_mks::nodegroup;
+use selectel_mks::task::schemas::Type as TaskType;
 use selectel_mks::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
 use term_table::row::Row;
 use term_table::table_cell::{Alignment, TableCell};
 use term_table::{Table, TableStyle};
 
-use crate::json;
+use crate::api;
+use crate::cluster;
+use crate::conf;
+use crate::confirm;
+use crate::logging;
+use crate::manifest;
+use crate::output;
+use crate::task;
+use crate::wait;
 
+/// Get the nodegroup, fields that the MKS client library doesn't have are got with the raw API
+/// client.
 pub(crate) fn get(
     client: &Client,
-    output: &str,
+    api_client: &api::Client,
+    format: &output::Format,
     cluster_id: &str,
     nodegroup_id: &str,
+    show_nodes: bool,
 ) -> Result<()> {
-    let nodegroup = client
-        .get_nodegroup(cluster_id, nodegroup_id)
-        .context("Failed to get nodegroup")?;
+    let nodegroup = logging::call(
+        "get_nodegroup",
+        &format!("cluster_id={} nodegroup_id={}", cluster_id, nodegroup_id),
+        || client.get_nodegroup(cluster_id, nodegroup_id),
+    )
+    .context("Failed to get nodegroup")?;
+    let details = logging::call(
+        "get_nodegroup_details",
+        &format!("cluster_id={} nodegroup_id={}", cluster_id, nodegroup_id),
+        || api_client.get_nodegroup_details(cluster_id, nodegroup_id),
+    )
+    .context("Failed to get nodegroup")?;
 
-    match output {
-        "table" => get_print_table(&nodegroup),
-        "json" => json::print_json(nodegroup)?,
-        _ => bail!("Unknown output format"),
-    };
+    output::print(
+        format,
+        &Details {
+            nodegroup,
+            details,
+            show_nodes,
+        },
+    )
+}
 
-    Ok(())
+/// Nodegroup with the table of its nodes.
+#[derive(Serialize)]
+struct Details {
+    #[serde(flatten)]
+    nodegroup: nodegroup::schemas::Nodegroup,
+
+    #[serde(flatten)]
+    details: api::NodegroupDetails,
+
+    /// Print the nodes table, it can be disabled for large nodegroups.
+    #[serde(skip)]
+    show_nodes: bool,
 }
 
-fn get_print_table(nodegroup: &nodegroup::schemas::Nodegroup) {
-    let updated_at = match &nodegroup.updated_at {
-        Some(time) => time.to_rfc3339(),
-        None => String::new(),
+impl output::Render for Details {
+    fn print_table(&self) {
+        let nodegroup = &self.nodegroup;
+        let updated_at = match &nodegroup.updated_at {
+            Some(time) => time.to_rfc3339(),
+            None => String::new(),
+        };
+
+        let mut table = Table::new();
+        table.style = TableStyle::simple();
+        table.separate_rows = false;
+
+        table.add_row(Row::new(vec![
+            TableCell::new("id"),
+            TableCell::new(&nodegroup.id),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("nodes_count"),
+            TableCell::new(nodegroup.nodes.len()),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("availability_zone"),
+            TableCell::new(&nodegroup.availability_zone),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("created_at"),
+            TableCell::new(nodegroup.created_at.to_rfc3339()),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("updated_at"),
+            TableCell::new(&updated_at),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("cluster_id"),
+            TableCell::new(&nodegroup.cluster_id),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("flavor_id"),
+            TableCell::new(&nodegroup.flavor_id),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("volume_gb"),
+            TableCell::new(nodegroup.volume_gb),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("volume_type"),
+            TableCell::new(&nodegroup.volume_type),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("local_volume"),
+            TableCell::new(nodegroup.local_volume),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("labels"),
+            TableCell::new(labels(nodegroup)),
+        ]));
+
+        let details = &self.details;
+        table.add_row(Row::new(vec![
+            TableCell::new("taints"),
+            TableCell::new(format_taints(&details.taints)),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("enable_autoscale"),
+            TableCell::new(details.enable_autoscale),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("autoscale_min_nodes"),
+            TableCell::new(format_count(details.autoscale_min_nodes)),
+        ]));
+
+        table.add_row(Row::new(vec![
+            TableCell::new("autoscale_max_nodes"),
+            TableCell::new(format_count(details.autoscale_max_nodes)),
+        ]));
+
+        // These fields are only returned for some nodegroups, e.g. the ones created with them.
+        if let Some(keypair_name) = &details.keypair_name {
+            table.add_row(Row::new(vec![
+                TableCell::new("keypair_name"),
+                TableCell::new(keypair_name),
+            ]));
+        }
+
+        if let Some(affinity_policy) = &details.affinity_policy {
+            table.add_row(Row::new(vec![
+                TableCell::new("affinity_policy"),
+                TableCell::new(affinity_policy),
+            ]));
+        }
+
+        if let Some(cpus) = details.cpus {
+            table.add_row(Row::new(vec![TableCell::new("cpus"), TableCell::new(cpus)]));
+        }
+
+        if let Some(ram_mb) = details.ram_mb {
+            table.add_row(Row::new(vec![
+                TableCell::new("ram_mb"),
+                TableCell::new(ram_mb),
+            ]));
+        }
+
+        println!("{}", table.render());
+
+        if !self.show_nodes || nodegroup.nodes.is_empty() {
+            return;
+        }
+
+        let mut nodes_table = Table::new();
+        nodes_table.style = TableStyle::simple();
+
+        nodes_table.add_row(Row::new(vec![
+            TableCell::new_with_alignment("id", 1, Alignment::Center),
+            TableCell::new_with_alignment("hostname", 1, Alignment::Center),
+            TableCell::new_with_alignment("ip", 1, Alignment::Center),
+            TableCell::new_with_alignment("created_at", 1, Alignment::Center),
+            TableCell::new_with_alignment("updated_at", 1, Alignment::Center),
+        ]));
+
+        for node in nodegroup.nodes.iter() {
+            let updated_at = match &node.updated_at {
+                Some(time) => time.to_rfc3339(),
+                None => String::new(),
+            };
+
+            nodes_table.add_row(Row::new(vec![
+                TableCell::new(&node.id),
+                TableCell::new(&node.hostname),
+                TableCell::new(&node.ip),
+                TableCell::new(node.created_at.to_rfc3339()),
+                TableCell::new(updated_at),
+            ]));
+        }
+
+        println!("{}", nodes_table.render());
+    }
+
+    fn names(&self) -> Option<Vec<String>> {
+        Some(vec![format!("nodegroup/{}", self.nodegroup.id)])
+    }
+}
+
+pub(crate) fn list(
+    client: &Client,
+    format: &output::Format,
+    table_opts: &output::TableOpts,
+    cluster_id: &str,
+) -> Result<()> {
+    let nodegroups = logging::call(
+        "list_nodegroups",
+        &format!("cluster_id={}", cluster_id),
+        || client.list_nodegroups(cluster_id),
+    )
+    .context("Failed to list nodegroups")?;
+
+    output::print_list(format, table_opts, &nodegroups)
+}
+
+impl output::Item for nodegroup::schemas::Nodegroup {
+    const KIND: &'static str = "nodegroup";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn fields() -> Vec<output::Field<Self>> {
+        vec![
+            output::Field::new("id", |nodegroup| nodegroup.id.clone()),
+            output::Field::new("nodes_count", |nodegroup| nodegroup.nodes.len().to_string()),
+            output::Field::new("availability_zone", |nodegroup| {
+                nodegroup.availability_zone.clone()
+            }),
+            output::Field::new("flavor_id", |nodegroup| nodegroup.flavor_id.clone()),
+            output::Field::new("volume_gb", |nodegroup| nodegroup.volume_gb.to_string()),
+            output::Field::new("volume_type", |nodegroup| nodegroup.volume_type.clone()),
+            output::Field::new("local_volume", |nodegroup| {
+                nodegroup.local_volume.to_string()
+            }),
+            output::Field::wide("labels", labels),
+            output::Field::wide("created_at", |nodegroup| nodegroup.created_at.to_rfc3339()),
+            output::Field::wide("updated_at", |nodegroup| {
+                nodegroup
+                    .updated_at
+                    .map(|time| time.to_rfc3339())
+                    .unwrap_or_default()
+            }),
+            output::Field::wide("cluster_id", |nodegroup| nodegroup.cluster_id.clone()),
+        ]
+    }
+}
+
+/// Format nodegroup labels as sorted "key=value" pairs.
+fn labels(nodegroup: &nodegroup::schemas::Nodegroup) -> String {
+    format_labels(&nodegroup.labels.clone().into_iter().collect())
+}
+
+fn format_labels(labels: &BTreeMap<String, String>) -> String {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+
+    labels.join(",")
+}
+
+fn format_count(count: Option<u32>) -> String {
+    count.map(|count| count.to_string()).unwrap_or_default()
+}
+
+fn format_taints(taints: &[Taint]) -> String {
+    let taints: Vec<String> = taints.iter().map(|taint| taint.to_string()).collect();
+
+    taints.join(",")
+}
+
+/// Create the nodegroup, it's created with the raw API client since taints are not supported by
+/// the MKS client library.
+pub(crate) fn create(
+    client: &Client,
+    api_client: &api::Client,
+    format: &output::Format,
+    cluster_id: &str,
+    opts: nodegroup::schemas::CreateOpts,
+    taints: Vec<Taint>,
+    wait_args: &conf::WaitArgs,
+) -> Result<()> {
+    let opts = api::CreateNodegroupOpts {
+        opts: &opts,
+        taints: &taints,
+        enable_autoscale: None,
+        autoscale_min_nodes: None,
+        autoscale_max_nodes: None,
     };
+    logging::dump("create_nodegroup request", &opts);
+    let tasks = task::track(
+        client,
+        cluster_id,
+        &TaskType::NodeGroupResize,
+        wait_args,
+        || {
+            logging::call(
+                "create_nodegroup",
+                &format!("cluster_id={}", cluster_id),
+                || api_client.create_nodegroup(cluster_id, &opts),
+            )
+            .context("Failed to create nodegroup")
+        },
+    )?;
 
-    let mut table = Table::new();
-    table.style = TableStyle::simple();
-    table.separate_rows = false;
+    task::print_started(format, &tasks)
+}
 
-    table.add_row(Row::new(vec![
-        TableCell::new("id"),
-        TableCell::new(&nodegroup.id),
-    ]));
+/// Kubernetes label of the nodegroup nodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Label {
+    key: String,
+    value: String,
+}
 
-    table.add_row(Row::new(vec![
-        TableCell::new("nodes_count"),
-        TableCell::new(nodegroup.nodes.len()),
-    ]));
+/// Parse a label and check it against the Kubernetes syntax.
+///
+/// The key is a name with an optional DNS subdomain prefix like "example.com/role", names and
+/// values are up to 63 alphanumeric characters, '-', '_' or '.' that start and end with
+/// alphanumeric ones, the value can also be empty.
+impl FromStr for Label {
+    type Err = String;
 
-    table.add_row(Row::new(vec![
-        TableCell::new("availability_zone"),
-        TableCell::new(&nodegroup.availability_zone),
-    ]));
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("invalid label \"{}\", expected <key>=<value>", s))?;
+        check_key("label", key)?;
+        check_value("label", value)?;
 
-    table.add_row(Row::new(vec![
-        TableCell::new("created_at"),
-        TableCell::new(&nodegroup.created_at.to_rfc3339()),
-    ]));
+        Ok(Label {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
 
-    table.add_row(Row::new(vec![
-        TableCell::new("updated_at"),
-        TableCell::new(&updated_at),
-    ]));
+/// Kubernetes taint of the nodegroup nodes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Taint {
+    pub(crate) key: String,
 
-    table.add_row(Row::new(vec![
-        TableCell::new("cluster_id"),
-        TableCell::new(&nodegroup.cluster_id),
-    ]));
+    #[serde(default)]
+    pub(crate) value: String,
 
-    table.add_row(Row::new(vec![
-        TableCell::new("flavor_id"),
-        TableCell::new(&nodegroup.flavor_id),
-    ]));
+    pub(crate) effect: String,
+}
 
-    table.add_row(Row::new(vec![
-        TableCell::new("volume_gb"),
-        TableCell::new(&nodegroup.volume_gb),
-    ]));
+// Effects of taints that are supported by Kubernetes.
+const TAINT_EFFECTS: [&str; 3] = ["NoSchedule", "PreferNoSchedule", "NoExecute"];
 
-    table.add_row(Row::new(vec![
-        TableCell::new("volume_type"),
-        TableCell::new(&nodegroup.volume_type),
-    ]));
+/// Parse a taint in the kubectl form "key=value:Effect" or "key:Effect".
+///
+/// The key and value follow the label syntax, the effect is NoSchedule, PreferNoSchedule or
+/// NoExecute.
+impl FromStr for Taint {
+    type Err = String;
 
-    table.add_row(Row::new(vec![
-        TableCell::new("local_volume"),
-        TableCell::new(&nodegroup.local_volume),
-    ]));
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key_value, effect) = s.rsplit_once(':').ok_or_else(|| {
+            format!(
+                "invalid taint \"{}\", expected <key>=<value>:<effect> or <key>:<effect>",
+                s
+            )
+        })?;
+        let (key, value) = key_value.split_once('=').unwrap_or((key_value, ""));
+        check_key("taint", key)?;
+        check_value("taint", value)?;
+        if !TAINT_EFFECTS.contains(&effect) {
+            return Err(format!(
+                "invalid taint effect \"{}\", expected one of {}",
+                effect,
+                TAINT_EFFECTS.join(", ")
+            ));
+        }
 
-    println!("{}", table.render());
+        Ok(Taint {
+            key: key.to_string(),
+            value: value.to_string(),
+            effect: effect.to_string(),
+        })
+    }
 }
 
-pub(crate) fn list(client: &Client, output: &str, cluster_id: &str) -> Result<()> {
-    let nodegroups = client
-        .list_nodegroups(cluster_id)
-        .context("Failed to list nodegroups")?;
+impl fmt::Display for Taint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.value.is_empty() {
+            write!(f, "{}:{}", self.key, self.effect)
+        } else {
+            write!(f, "{}={}:{}", self.key, self.value, self.effect)
+        }
+    }
+}
 
-    match output {
-        "table" => list_print_table(&nodegroups),
-        "json" => json::print_json(nodegroups)?,
-        _ => bail!("Unknown output format"),
+fn check_key(kind: &str, key: &str) -> Result<(), String> {
+    let name = match key.rsplit_once('/') {
+        Some((prefix, name)) => {
+            if !is_dns_subdomain(prefix) {
+                return Err(format!(
+                    "invalid {} key \"{}\", prefix must be a lowercase DNS subdomain",
+                    kind, key
+                ));
+            }
+            name
+        }
+        None => key,
     };
+    if !is_label_name(name) {
+        return Err(format!(
+            "invalid {} key \"{}\", name must be up to 63 alphanumeric characters, \
+             '-', '_' or '.' that start and end with an alphanumeric character",
+            kind, key
+        ));
+    }
+
+    Ok(())
+}
+
+fn check_value(kind: &str, value: &str) -> Result<(), String> {
+    if !value.is_empty() && !is_label_name(value) {
+        return Err(format!(
+            "invalid {} value \"{}\", it must be empty or up to 63 alphanumeric \
+             characters, '-', '_' or '.' that start and end with an alphanumeric character",
+            kind, value
+        ));
+    }
 
     Ok(())
 }
 
-fn list_print_table(nodegroups: &[nodegroup::schemas::Nodegroup]) {
+fn is_label_name(name: &str) -> bool {
+    name.len() <= 63
+        && name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name.ends_with(|c: char| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+fn is_dns_subdomain(prefix: &str) -> bool {
+    prefix.len() <= 253
+        && prefix.split('.').all(|part| {
+            part.len() <= 63
+                && part.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+                && part.ends_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        })
+}
+
+/// Collect labels into the map used by the MKS API, later labels replace earlier ones.
+pub(crate) fn labels_map(labels: Vec<Label>) -> HashMap<String, String> {
+    labels
+        .into_iter()
+        .map(|label| (label.key, label.value))
+        .collect()
+}
+
+/// Nodegroup changes, labels and taints are merged into the existing ones.
+#[derive(Debug)]
+pub(crate) struct Update {
+    pub(crate) nodes_count: Option<u32>,
+    pub(crate) labels: Vec<Label>,
+    pub(crate) remove_labels: Vec<String>,
+    pub(crate) taints: Vec<Taint>,
+
+    /// Taints to remove in the "key" or "key:Effect" form.
+    pub(crate) remove_taints: Vec<String>,
+
+    pub(crate) enable_autoscale: Option<bool>,
+    pub(crate) autoscale_min_nodes: Option<u32>,
+    pub(crate) autoscale_max_nodes: Option<u32>,
+}
+
+/// Nodegroup fields that can be changed by `set`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MutableFields {
+    nodes_count: u32,
+    labels: BTreeMap<String, String>,
+    taints: Vec<Taint>,
+    enable_autoscale: bool,
+    autoscale_min_nodes: Option<u32>,
+    autoscale_max_nodes: Option<u32>,
+}
+
+impl Update {
+    /// Merge the changes into the existing fields and check the autoscaling bounds.
+    ///
+    /// Taints are identified by their key and effect like in Kubernetes, so a new taint replaces
+    /// the existing one and a removed taint is either a key of taints with any effect or
+    /// "key:Effect".
+    fn merge(&self, existing: &MutableFields) -> Result<MutableFields> {
+        let mut labels = existing.labels.clone();
+        for key in self.remove_labels.iter() {
+            labels.remove(key);
+        }
+        for label in self.labels.iter() {
+            labels.insert(label.key.clone(), label.value.clone());
+        }
+
+        let mut taints = existing.taints.clone();
+        taints.retain(|taint| {
+            !self.remove_taints.iter().any(|removed| {
+                *removed == taint.key || *removed == format!("{}:{}", taint.key, taint.effect)
+            })
+        });
+        for taint in self.taints.iter() {
+            taints.retain(|existing| existing.key != taint.key || existing.effect != taint.effect);
+            taints.push(taint.clone());
+        }
+
+        let enable_autoscale = self.enable_autoscale.unwrap_or(existing.enable_autoscale);
+        let autoscale_min_nodes = self.autoscale_min_nodes.or(existing.autoscale_min_nodes);
+        let autoscale_max_nodes = self.autoscale_max_nodes.or(existing.autoscale_max_nodes);
+        if let (Some(min), Some(max)) = (autoscale_min_nodes, autoscale_max_nodes) {
+            if min > max {
+                bail!(
+                    "Minimum count of nodes {} is greater than the maximum count {}",
+                    min,
+                    max
+                );
+            }
+        }
+        if enable_autoscale && (autoscale_min_nodes.is_none() || autoscale_max_nodes.is_none()) {
+            bail!("Autoscaling requires --autoscale-min-nodes and --autoscale-max-nodes");
+        }
+
+        Ok(MutableFields {
+            nodes_count: self.nodes_count.unwrap_or(existing.nodes_count),
+            labels,
+            taints,
+            enable_autoscale,
+            autoscale_min_nodes,
+            autoscale_max_nodes,
+        })
+    }
+}
+
+/// Update the nodegroup, print all mutable fields before and after the update to stderr and
+/// the started tasks.
+///
+/// Labels, taints and autoscaling bounds are updated before the resize, and the resize waits
+/// until the cluster is ACTIVE again even without the --wait flag since changes aren't accepted
+/// while the cluster is updated.
+pub(crate) fn set(
+    client: &Client,
+    api_client: &api::Client,
+    format: &output::Format,
+    cluster_id: &str,
+    nodegroup_id: &str,
+    update: Update,
+    wait_args: &conf::WaitArgs,
+) -> Result<()> {
+    if update.nodes_count.is_none()
+        && update.labels.is_empty()
+        && update.remove_labels.is_empty()
+        && update.taints.is_empty()
+        && update.remove_taints.is_empty()
+        && update.enable_autoscale.is_none()
+        && update.autoscale_min_nodes.is_none()
+        && update.autoscale_max_nodes.is_none()
+    {
+        bail!(
+            "Nothing to update, provide --nodes-count, --label, --remove-label, --taint, \
+             --remove-taint, --enable-autoscale, --autoscale-min-nodes or --autoscale-max-nodes"
+        );
+    }
+
+    let nodegroup = logging::call(
+        "get_nodegroup",
+        &format!("cluster_id={} nodegroup_id={}", cluster_id, nodegroup_id),
+        || client.get_nodegroup(cluster_id, nodegroup_id),
+    )
+    .context("Failed to get nodegroup")?;
+    let details = logging::call(
+        "get_nodegroup_details",
+        &format!("cluster_id={} nodegroup_id={}", cluster_id, nodegroup_id),
+        || api_client.get_nodegroup_details(cluster_id, nodegroup_id),
+    )
+    .context("Failed to get nodegroup")?;
+
+    let existing = MutableFields {
+        nodes_count: nodegroup.nodes.len() as u32,
+        labels: nodegroup
+            .labels
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect(),
+        taints: details.taints,
+        enable_autoscale: details.enable_autoscale,
+        autoscale_min_nodes: details.autoscale_min_nodes,
+        autoscale_max_nodes: details.autoscale_max_nodes,
+    };
+    let updated = update.merge(&existing)?;
+
+    let nodes_count = Some(updated.nodes_count).filter(|&desired| desired != existing.nodes_count);
+    let update_labels = updated.labels != existing.labels;
+    let update_taints = updated.taints != existing.taints;
+    let update_autoscale = updated.enable_autoscale != existing.enable_autoscale
+        || updated.autoscale_min_nodes != existing.autoscale_min_nodes
+        || updated.autoscale_max_nodes != existing.autoscale_max_nodes;
+
+    if nodes_count.is_none() && !update_labels && !update_taints && !update_autoscale {
+        eprintln!("Nodegroup {} is up to date", nodegroup_id);
+        return Ok(());
+    }
+    print_changes(&[
+        (
+            "nodes_count",
+            existing.nodes_count.to_string(),
+            updated.nodes_count.to_string(),
+        ),
+        (
+            "labels",
+            format_labels(&existing.labels),
+            format_labels(&updated.labels),
+        ),
+        (
+            "taints",
+            format_taints(&existing.taints),
+            format_taints(&updated.taints),
+        ),
+        (
+            "enable_autoscale",
+            existing.enable_autoscale.to_string(),
+            updated.enable_autoscale.to_string(),
+        ),
+        (
+            "autoscale_min_nodes",
+            format_count(existing.autoscale_min_nodes),
+            format_count(updated.autoscale_min_nodes),
+        ),
+        (
+            "autoscale_max_nodes",
+            format_count(existing.autoscale_max_nodes),
+            format_count(updated.autoscale_max_nodes),
+        ),
+    ]);
+
+    let mut tasks = Vec::new();
+
+    let update_opts = api::UpdateNodegroupOpts {
+        labels: if update_labels {
+            Some(manifest::labels(&updated.labels))
+        } else {
+            None
+        },
+        taints: if update_taints {
+            Some(updated.taints)
+        } else {
+            None
+        },
+        enable_autoscale: if update_autoscale {
+            Some(updated.enable_autoscale)
+        } else {
+            None
+        },
+        autoscale_min_nodes: updated.autoscale_min_nodes.filter(|_| update_autoscale),
+        autoscale_max_nodes: updated.autoscale_max_nodes.filter(|_| update_autoscale),
+    };
+    let update_nodegroup = update_labels || update_taints || update_autoscale;
+    if update_nodegroup {
+        logging::dump("update_nodegroup request", &update_opts);
+        tasks.extend(task::track(
+            client,
+            cluster_id,
+            &TaskType::UpdateNodegroupLabels,
+            wait_args,
+            || {
+                logging::call(
+                    "update_nodegroup",
+                    &format!("cluster_id={} nodegroup_id={}", cluster_id, nodegroup_id),
+                    || api_client.update_nodegroup(cluster_id, nodegroup_id, &update_opts),
+                )
+                .context("Failed to update nodegroup")
+            },
+        )?);
+    }
+
+    if let Some(desired) = nodes_count {
+        if update_nodegroup {
+            let between = wait::WaitOpts::new(wait_args.timeout, wait_args.poll_interval);
+            cluster::wait_active(client, cluster_id, &between)?;
+        }
+
+        let resize_opts = &nodegroup::schemas::ResizeOpts::new(desired);
+        logging::dump("resize_nodegroup request", resize_opts);
+        tasks.extend(task::track(
+            client,
+            cluster_id,
+            &TaskType::NodeGroupResize,
+            wait_args,
+            || {
+                logging::call(
+                    "resize_nodegroup",
+                    &format!("cluster_id={} nodegroup_id={}", cluster_id, nodegroup_id),
+                    || client.resize_nodegroup(cluster_id, nodegroup_id, resize_opts),
+                )
+                .context("Failed to resize nodegroup")
+            },
+        )?);
+    }
+
+    task::print_started(format, &tasks)
+}
+
+/// Print the fields with their current and new values to stderr.
+fn print_changes(changes: &[(&str, String, String)]) {
     let mut table = Table::new();
     table.style = TableStyle::simple();
 
     table.add_row(Row::new(vec![
-        TableCell::new_with_alignment("id", 1, Alignment::Center),
-        TableCell::new_with_alignment("nodes_count", 1, Alignment::Center),
-        TableCell::new_with_alignment("availability_zone", 1, Alignment::Center),
-        TableCell::new_with_alignment("flavor_id", 1, Alignment::Center),
-        TableCell::new_with_alignment("volume_gb", 1, Alignment::Center),
-        TableCell::new_with_alignment("volume_type", 1, Alignment::Center),
-        TableCell::new_with_alignment("local_volume", 1, Alignment::Center),
+        TableCell::new_with_alignment("field", 1, Alignment::Center),
+        TableCell::new_with_alignment("before", 1, Alignment::Center),
+        TableCell::new_with_alignment("after", 1, Alignment::Center),
     ]));
 
-    for nodegroup in nodegroups.iter() {
+    for (field, before, after) in changes.iter() {
         table.add_row(Row::new(vec![
-            TableCell::new(&nodegroup.id),
-            TableCell::new(nodegroup.nodes.len()),
-            TableCell::new(&nodegroup.availability_zone),
-            TableCell::new(&nodegroup.flavor_id),
-            TableCell::new(&nodegroup.volume_gb),
-            TableCell::new(&nodegroup.volume_type),
-            TableCell::new(&nodegroup.local_volume),
+            TableCell::new(field),
+            TableCell::new(before),
+            TableCell::new(after),
         ]));
     }
 
-    println!("{}", table.render());
+    eprintln!("{}", table.render());
 }
 
-pub(crate) fn create(
+/// Reinstall all nodes of the nodegroup in batches of `max_unavailable` nodes.
+///
+/// The API rejects changes of a cluster that isn't ACTIVE, so nodes of a batch are reinstalled
+/// one at a time when the cluster is ACTIVE again. The next batch waits until the reinstall tasks
+/// of the current batch are DONE, the rollout is aborted if any of them fails or doesn't appear.
+pub(crate) fn reinstall(
     client: &Client,
     cluster_id: &str,
-    opts: nodegroup::schemas::CreateOpts,
+    nodegroup_id: &str,
+    max_unavailable: usize,
+    wait_opts: &wait::WaitOpts,
 ) -> Result<()> {
-    client
-        .create_nodegroup(&cluster_id, &opts)
-        .context("Failed to create nodegroup")?;
+    if max_unavailable == 0 {
+        bail!("Maximum count of unavailable nodes must be positive");
+    }
+
+    let nodegroup = logging::call(
+        "get_nodegroup",
+        &format!("cluster_id={} nodegroup_id={}", cluster_id, nodegroup_id),
+        || client.get_nodegroup(cluster_id, nodegroup_id),
+    )
+    .context("Failed to get nodegroup")?;
+
+    let total = nodegroup.nodes.len();
+    let mut reinstalled = 0;
+    let aborted = |reinstalled: usize| {
+        format!(
+            "Aborted nodegroup reinstall, {} of {} node(s) are reinstalled",
+            reinstalled, total
+        )
+    };
+    for batch in nodegroup.nodes.chunks(max_unavailable) {
+        let mut tasks = Vec::with_capacity(batch.len());
+        for node in batch.iter() {
+            cluster::wait_active(client, cluster_id, wait_opts)
+                .with_context(|| aborted(reinstalled))?;
+
+            let known_tasks = task::ids(client, cluster_id)?;
+            logging::call(
+                "reinstall_node",
+                &format!(
+                    "cluster_id={} nodegroup_id={} node_id={}",
+                    cluster_id, nodegroup_id, node.id
+                ),
+                || client.reinstall_node(cluster_id, nodegroup_id, &node.id),
+            )
+            .with_context(|| format!("Failed to reinstall node {}", node.hostname))
+            .with_context(|| aborted(reinstalled))?;
+            eprintln!("Reinstalling node {} ({})", node.hostname, node.id);
+
+            // Every node of the batch must be tracked before the next batch is started.
+            let started = task::wait_started(
+                client,
+                cluster_id,
+                &known_tasks,
+                &TaskType::NodeReinstall,
+                1,
+                wait_opts,
+            )
+            .with_context(|| format!("Reinstall task of node {} is not found", node.hostname))
+            .with_context(|| aborted(reinstalled))?;
+            tasks.extend(started);
+        }
+
+        for started_task in tasks.iter() {
+            task::wait_done(client, cluster_id, &started_task.id, wait_opts)
+                .with_context(|| aborted(reinstalled))?;
+            reinstalled += 1;
+        }
+        eprintln!("Reinstalled {} of {} node(s)", reinstalled, total);
+    }
+
+    cluster::wait_active(client, cluster_id, wait_opts)?;
+    println!(
+        "Reinstalled {} node(s) of nodegroup {}",
+        total, nodegroup_id
+    );
 
     Ok(())
 }
 
-pub(crate) fn set(
+/// Delete the nodegroup, the deletion is confirmed on the terminal unless `yes` is set.
+pub(crate) fn delete(
     client: &Client,
+    format: &output::Format,
     cluster_id: &str,
     nodegroup_id: &str,
-    nodes_count: Option<u32>,
+    yes: bool,
+    wait_args: &conf::WaitArgs,
 ) -> Result<()> {
-    if let Some(desired) = nodes_count {
-        let resize_opts = &nodegroup::schemas::ResizeOpts::new(desired);
-        client
-            .resize_nodegroup(cluster_id, nodegroup_id, resize_opts)
-            .context("Failed to resize nodegroup")?;
+    if !yes {
+        let nodegroup = logging::call(
+            "get_nodegroup",
+            &format!("cluster_id={} nodegroup_id={}", cluster_id, nodegroup_id),
+            || client.get_nodegroup(cluster_id, nodegroup_id),
+        )
+        .context("Failed to get nodegroup")?;
+
+        eprintln!(
+            "Nodegroup {} in {} is {} old, it has {} node(s)",
+            nodegroup.id,
+            nodegroup.availability_zone,
+            confirm::age(nodegroup.created_at.timestamp()),
+            nodegroup.nodes.len()
+        );
+        if !confirm::confirm("Delete the nodegroup?", None)? {
+            bail!("Nodegroup deletion is cancelled");
+        }
     }
 
-    Ok(())
+    let tasks = task::track(
+        client,
+        cluster_id,
+        &TaskType::NodeGroupResize,
+        wait_args,
+        || {
+            logging::call(
+                "delete_nodegroup",
+                &format!("cluster_id={} nodegroup_id={}", cluster_id, nodegroup_id),
+                || client.delete_nodegroup(cluster_id, nodegroup_id),
+            )
+            .context("Failed to delete nodegroup")
+        },
+    )?;
+
+    task::print_started(format, &tasks)
 }
 
-pub(crate) fn delete(client: &Client, cluster_id: &str, nodegroup_id: &str) -> Result<()> {
-    client
-        .delete_nodegroup(cluster_id, nodegroup_id)
-        .context("Failed to delete nodegroup")?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(())
+    fn label(s: &str) -> Result<(String, String), String> {
+        s.parse::<Label>().map(|label| (label.key, label.value))
+    }
+
+    fn taint(key: &str, value: &str, effect: &str) -> Taint {
+        Taint {
+            key: key.to_string(),
+            value: value.to_string(),
+            effect: effect.to_string(),
+        }
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(label("role=web"), Ok(("role".into(), "web".into())));
+        assert_eq!(label("a.b/c=d"), Ok(("a.b/c".into(), "d".into())));
+        assert_eq!(label("role="), Ok(("role".into(), "".into())));
+        assert!(label("a=b=c").unwrap_err().contains("value \"b=c\""));
+        assert!(label("role").is_err());
+        assert!(label("=web").is_err());
+        assert!(label("-role=web").is_err());
+        assert!(label("role=web-").is_err());
+    }
+
+    #[test]
+    fn label_length() {
+        let name = "a".repeat(63);
+        assert!(label(&format!("{}={}", name, name)).is_ok());
+
+        let long = "a".repeat(64);
+        assert!(label(&format!("{}=web", long)).is_err());
+        assert!(label(&format!("role={}", long)).is_err());
+        assert!(label(&format!("{}/role=web", "a".repeat(254))).is_err());
+    }
+
+    #[test]
+    fn label_prefixes() {
+        assert!(label("example.com/role=web").is_ok());
+        assert!(label("Example.com/role=web").is_err());
+        assert!(label("a_b/role=web").is_err());
+        assert!(label("/role=web").is_err());
+        assert!(label("a.b/=web").is_err());
+    }
+
+    #[test]
+    fn taints() {
+        assert_eq!("a=b:NoSchedule".parse(), Ok(taint("a", "b", "NoSchedule")));
+        assert_eq!("a:NoExecute".parse(), Ok(taint("a", "", "NoExecute")));
+        assert_eq!("a=:NoExecute".parse(), Ok(taint("a", "", "NoExecute")));
+        assert_eq!(
+            "a.b/c=d:PreferNoSchedule".parse(),
+            Ok(taint("a.b/c", "d", "PreferNoSchedule"))
+        );
+        assert!("a=b".parse::<Taint>().is_err());
+        assert!("A.b/c:NoSchedule".parse::<Taint>().is_err());
+        assert!(format!("{}:NoSchedule", "a".repeat(64))
+            .parse::<Taint>()
+            .is_err());
+    }
+
+    #[test]
+    fn taint_effects() {
+        let err = "a=b:NoScheduled".parse::<Taint>().unwrap_err();
+        assert!(err.contains("effect \"NoScheduled\""));
+        assert!("a=b:noschedule".parse::<Taint>().is_err());
+        assert!("a=b:".parse::<Taint>().is_err());
+    }
+
+    #[test]
+    fn taint_display() {
+        assert_eq!(taint("a", "b", "NoSchedule").to_string(), "a=b:NoSchedule");
+        assert_eq!(taint("a", "", "NoExecute").to_string(), "a:NoExecute");
+    }
+
+    fn existing() -> MutableFields {
+        MutableFields {
+            nodes_count: 3,
+            labels: vec![("role".to_string(), "web".to_string())]
+                .into_iter()
+                .collect(),
+            taints: vec![
+                taint("a", "b", "NoSchedule"),
+                taint("a", "", "NoExecute"),
+                taint("c", "d", "NoSchedule"),
+            ],
+            enable_autoscale: false,
+            autoscale_min_nodes: None,
+            autoscale_max_nodes: None,
+        }
+    }
+
+    fn update() -> Update {
+        Update {
+            nodes_count: None,
+            labels: Vec::new(),
+            remove_labels: Vec::new(),
+            taints: Vec::new(),
+            remove_taints: Vec::new(),
+            enable_autoscale: None,
+            autoscale_min_nodes: None,
+            autoscale_max_nodes: None,
+        }
+    }
+
+    #[test]
+    fn merge_nothing() {
+        assert_eq!(update().merge(&existing()).unwrap(), existing());
+    }
+
+    #[test]
+    fn merge_labels() {
+        let merged = Update {
+            nodes_count: Some(5),
+            labels: vec!["tier=1".parse().unwrap(), "role=db".parse().unwrap()],
+            remove_labels: vec!["role".to_string(), "missing".to_string()],
+            ..update()
+        }
+        .merge(&existing())
+        .unwrap();
+
+        assert_eq!(merged.nodes_count, 5);
+        assert_eq!(format_labels(&merged.labels), "role=db,tier=1");
+    }
+
+    #[test]
+    fn merge_removed_taints() {
+        let merged = Update {
+            remove_taints: vec!["a".to_string()],
+            ..update()
+        }
+        .merge(&existing())
+        .unwrap();
+        assert_eq!(merged.taints, vec![taint("c", "d", "NoSchedule")]);
+
+        let merged = Update {
+            remove_taints: vec!["a:NoExecute".to_string(), "c:NoExecute".to_string()],
+            ..update()
+        }
+        .merge(&existing())
+        .unwrap();
+        assert_eq!(
+            merged.taints,
+            vec![taint("a", "b", "NoSchedule"), taint("c", "d", "NoSchedule")]
+        );
+    }
+
+    #[test]
+    fn merge_replaced_taints() {
+        let merged = Update {
+            taints: vec![
+                "a=e:NoSchedule".parse().unwrap(),
+                "c=d:NoExecute".parse().unwrap(),
+            ],
+            ..update()
+        }
+        .merge(&existing())
+        .unwrap();
+
+        assert_eq!(
+            format_taints(&merged.taints),
+            "a:NoExecute,c=d:NoSchedule,a=e:NoSchedule,c=d:NoExecute"
+        );
+    }
+
+    #[test]
+    fn merge_autoscale() {
+        let merged = Update {
+            enable_autoscale: Some(true),
+            autoscale_min_nodes: Some(1),
+            autoscale_max_nodes: Some(3),
+            ..update()
+        }
+        .merge(&existing())
+        .unwrap();
+        assert!(merged.enable_autoscale);
+        assert_eq!(merged.autoscale_min_nodes, Some(1));
+        assert_eq!(merged.autoscale_max_nodes, Some(3));
+
+        // Bounds are kept when only one of them is changed.
+        let merged = Update {
+            autoscale_max_nodes: Some(5),
+            ..update()
+        }
+        .merge(&merged)
+        .unwrap();
+        assert_eq!(merged.autoscale_min_nodes, Some(1));
+        assert_eq!(merged.autoscale_max_nodes, Some(5));
+
+        let err = Update {
+            autoscale_min_nodes: Some(6),
+            ..update()
+        }
+        .merge(&merged)
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Minimum count of nodes 6 is greater than the maximum count 5"
+        );
+    }
+
+    #[test]
+    fn merge_autoscale_without_bounds() {
+        let err = Update {
+            enable_autoscale: Some(true),
+            autoscale_min_nodes: Some(1),
+            ..update()
+        }
+        .merge(&existing())
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Autoscaling requires --autoscale-min-nodes and --autoscale-max-nodes"
+        );
+
+        let merged = Update {
+            autoscale_min_nodes: Some(1),
+            ..update()
+        }
+        .merge(&existing())
+        .unwrap();
+        assert!(!merged.enable_autoscale);
+        assert_eq!(merged.autoscale_min_nodes, Some(1));
+    }
 }
@@ -0,0 +1,415 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use term_table::row::Row;
+use term_table::table_cell::{Alignment, TableCell};
+use term_table::{Table, TableStyle};
+
+use crate::conf;
+use crate::json;
+use crate::jsonpath;
+use crate::yaml;
+
+/// Output format of the command results.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) enum Format {
+    #[default]
+    Table,
+
+    /// `wide`: table with additional columns in lists.
+    Wide,
+
+    Json,
+    Yaml,
+
+    /// `jsonpath=<template>`: fields selected by the kubectl-style JSONPath template.
+    JsonPath(jsonpath::Template),
+
+    /// `custom-columns=<header>:<path>,...`: table with columns selected by JSONPath expressions.
+    CustomColumns(Vec<Column>),
+
+    /// `name`: resource kind and identifier only.
+    Name,
+}
+
+/// Column of the custom columns table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Column {
+    header: String,
+    path: jsonpath::Path,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(template) = s.strip_prefix("jsonpath=") {
+            return Ok(Format::JsonPath(jsonpath::Template::parse(template)?));
+        }
+        if let Some(columns) = s.strip_prefix("custom-columns=") {
+            return Ok(Format::CustomColumns(parse_columns(columns)?));
+        }
+
+        match s {
+            "table" => Ok(Format::Table),
+            "wide" => Ok(Format::Wide),
+            "json" => Ok(Format::Json),
+            "yaml" => Ok(Format::Yaml),
+            "name" => Ok(Format::Name),
+            _ => Err(format!(
+                "unknown output format \"{}\", can be either of table, wide, json, yaml, name, \
+                 jsonpath=<template> or custom-columns=<header>:<path>,...",
+                s
+            )),
+        }
+    }
+}
+
+fn parse_columns(columns: &str) -> Result<Vec<Column>, String> {
+    columns
+        .split(',')
+        .map(|column| match column.split_once(':') {
+            Some((header, path)) if !header.is_empty() => {
+                let path = path.trim();
+                let path = path
+                    .strip_prefix('{')
+                    .and_then(|path| path.strip_suffix('}'))
+                    .unwrap_or(path);
+
+                Ok(Column {
+                    header: header.to_string(),
+                    path: jsonpath::Path::parse(path)?,
+                })
+            }
+            _ => Err(format!(
+                "invalid custom column \"{}\", expected <header>:<path>",
+                column
+            )),
+        })
+        .collect()
+}
+
+/// Command result that can be printed in every output format.
+///
+/// Serialized formats are provided by serde, so implementations only describe their table.
+pub(crate) trait Render: Serialize {
+    fn print_table(&self);
+
+    /// Resources in the "kind/identifier" form used by the name output format.
+    fn names(&self) -> Option<Vec<String>> {
+        None
+    }
+}
+
+/// Resource that can be printed as a row of a list table.
+pub(crate) trait Item: Serialize + Sized {
+    /// Resource kind used by the name output format.
+    const KIND: &'static str;
+
+    fn id(&self) -> &str;
+
+    /// All columns of the list table, wide ones are printed only with the wide output format.
+    fn fields() -> Vec<Field<Self>>;
+}
+
+/// Column of a list table.
+pub(crate) struct Field<T> {
+    name: &'static str,
+    wide: bool,
+    value: fn(&T) -> String,
+}
+
+impl<T> Field<T> {
+    pub(crate) fn new(name: &'static str, value: fn(&T) -> String) -> Field<T> {
+        Field {
+            name,
+            wide: false,
+            value,
+        }
+    }
+
+    pub(crate) fn wide(name: &'static str, value: fn(&T) -> String) -> Field<T> {
+        Field {
+            name,
+            wide: true,
+            value,
+        }
+    }
+}
+
+/// Options of the list tables.
+#[derive(Debug, Default)]
+pub(crate) struct TableOpts {
+    /// Columns to print in their order instead of the default ones.
+    pub(crate) columns: Option<Vec<String>>,
+
+    pub(crate) no_headers: bool,
+}
+
+impl From<conf::ListOpts> for TableOpts {
+    fn from(list_opts: conf::ListOpts) -> TableOpts {
+        TableOpts {
+            columns: list_opts.columns,
+            no_headers: list_opts.no_headers,
+        }
+    }
+}
+
+/// Selector of the list items by the values of their table columns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Filter {
+    field: String,
+    value: String,
+
+    /// Match values containing the filter value instead of the equal ones.
+    contains: bool,
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid filter \"{}\", expected <field>=<value> or <field>~=<value>",
+                s
+            )
+        };
+
+        let (selector, value) = s.split_once('=').ok_or_else(invalid)?;
+        let (field, contains) = match selector.strip_suffix('~') {
+            Some(field) => (field, true),
+            None => (selector, false),
+        };
+        if field.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Filter {
+            field: field.to_string(),
+            value: value.to_string(),
+            contains,
+        })
+    }
+}
+
+/// Check the list table options before the items are requested.
+///
+/// Columns must be known fields, and both options are only supported by the table output formats
+/// since serialized formats print all fields.
+pub(crate) fn check_list<T: Item>(format: &Format, table_opts: &TableOpts) -> Result<()> {
+    let table_format = matches!(format, Format::Table | Format::Wide);
+    if !table_format && (table_opts.columns.is_some() || table_opts.no_headers) {
+        bail!(
+            "Options --columns and --no-headers are only supported by table and wide output \
+             formats"
+        );
+    }
+
+    if let Some(columns) = &table_opts.columns {
+        let all_fields = T::fields();
+        for column in columns.iter() {
+            find_field(&all_fields, column)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that all filters select known fields, it's done before the items are requested.
+pub(crate) fn check_filters<T: Item>(filters: &[Filter]) -> Result<()> {
+    let all_fields = T::fields();
+    for filter in filters.iter() {
+        find_field(&all_fields, &filter.field)?;
+    }
+
+    Ok(())
+}
+
+/// Keep only the items matching all filters.
+pub(crate) fn filter<T: Item>(items: Vec<T>, filters: &[Filter]) -> Result<Vec<T>> {
+    let all_fields = T::fields();
+    let selectors = filters
+        .iter()
+        .map(|filter| Ok((find_field(&all_fields, &filter.field)?, filter)))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(items
+        .into_iter()
+        .filter(|item| {
+            selectors.iter().all(|(field, filter)| {
+                let value = (field.value)(item);
+                if filter.contains {
+                    value.contains(&filter.value)
+                } else {
+                    value == filter.value
+                }
+            })
+        })
+        .collect())
+}
+
+/// Print the command result in the output format.
+pub(crate) fn print<T: Render>(format: &Format, data: &T) -> Result<()> {
+    match format {
+        Format::Table | Format::Wide => data.print_table(),
+        Format::Name => match data.names() {
+            Some(names) => names.iter().for_each(|name| println!("{}", name)),
+            None => bail!("Output format name is not supported by this command"),
+        },
+        _ => print_serialized(format, data)?,
+    };
+
+    Ok(())
+}
+
+/// Print the list in the output format.
+pub(crate) fn print_list<T: Item>(
+    format: &Format,
+    table_opts: &TableOpts,
+    items: &[T],
+) -> Result<()> {
+    match format {
+        Format::Table => print_list_table(items, false, table_opts)?,
+        Format::Wide => print_list_table(items, true, table_opts)?,
+        Format::Name => items
+            .iter()
+            .for_each(|item| println!("{}/{}", T::KIND, item.id())),
+        _ => print_serialized(format, &items)?,
+    };
+
+    Ok(())
+}
+
+/// Print the serialized command result.
+///
+/// JSONPath templates are evaluated over the serialized result, lists are wrapped into an object
+/// with the "items" field and no newline is added like in kubectl.
+fn print_serialized<T: Serialize>(format: &Format, data: &T) -> Result<()> {
+    match format {
+        Format::Json => json::print_json(data)?,
+        Format::Yaml => yaml::print_yaml(data)?,
+        Format::JsonPath(template) => {
+            let value = match to_value(data)? {
+                Value::Array(items) => json!({ "items": items }),
+                value => value,
+            };
+            print!("{}", template.render(&value));
+        }
+        Format::CustomColumns(columns) => {
+            let rows = match to_value(data)? {
+                Value::Array(items) => items,
+                value => vec![value],
+            };
+            print_columns(columns, &rows);
+        }
+        // Tables and names are printed by the resources.
+        Format::Table | Format::Wide | Format::Name => unreachable!(),
+    };
+
+    Ok(())
+}
+
+fn to_value<T: Serialize>(data: &T) -> Result<Value> {
+    serde_json::to_value(data).context("Failed to serialize output")
+}
+
+fn print_list_table<T: Item>(items: &[T], wide: bool, table_opts: &TableOpts) -> Result<()> {
+    let all_fields = T::fields();
+    let fields: Vec<&Field<T>> = match &table_opts.columns {
+        Some(columns) => columns
+            .iter()
+            .map(|column| find_field(&all_fields, column))
+            .collect::<Result<_>>()?,
+        None => all_fields
+            .iter()
+            .filter(|field| wide || !field.wide)
+            .collect(),
+    };
+
+    let rows: Vec<Vec<String>> = items
+        .iter()
+        .map(|item| fields.iter().map(|field| (field.value)(item)).collect())
+        .collect();
+
+    if table_opts.no_headers {
+        print_aligned(&rows);
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.style = TableStyle::simple();
+
+    table.add_row(Row::new(
+        fields
+            .iter()
+            .map(|field| TableCell::new_with_alignment(field.name, 1, Alignment::Center))
+            .collect::<Vec<_>>(),
+    ));
+
+    for row in rows.iter() {
+        table.add_row(Row::new(row.iter().map(TableCell::new).collect::<Vec<_>>()));
+    }
+
+    println!("{}", table.render());
+
+    Ok(())
+}
+
+fn find_field<'a, T>(fields: &'a [Field<T>], name: &str) -> Result<&'a Field<T>> {
+    fields
+        .iter()
+        .find(|field| field.name == name)
+        .ok_or_else(|| {
+            let names: Vec<&str> = fields.iter().map(|field| field.name).collect();
+            anyhow!(
+                "Unknown field \"{}\", can be either of {}",
+                name,
+                names.join(", ")
+            )
+        })
+}
+
+fn print_columns(columns: &[Column], rows: &[Value]) {
+    let mut lines: Vec<Vec<String>> =
+        vec![columns.iter().map(|column| column.header.clone()).collect()];
+    for row in rows.iter() {
+        lines.push(
+            columns
+                .iter()
+                .map(|column| {
+                    let values: Vec<String> = column
+                        .path
+                        .select(row, row)
+                        .into_iter()
+                        .map(jsonpath::format_value)
+                        .collect();
+                    if values.is_empty() {
+                        "<none>".to_string()
+                    } else {
+                        values.join(",")
+                    }
+                })
+                .collect(),
+        );
+    }
+
+    print_aligned(&lines);
+}
+
+/// Print lines as plain columns separated by spaces.
+fn print_aligned(lines: &[Vec<String>]) {
+    let count = lines.first().map_or(0, Vec::len);
+    let widths: Vec<usize> = (0..count)
+        .map(|i| lines.iter().map(|line| line[i].len()).max().unwrap_or(0))
+        .collect();
+    for line in lines.iter() {
+        let cells: Vec<String> = line
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        println!("{}", cells.join("   ").trim_end());
+    }
+}
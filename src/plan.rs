@@ -0,0 +1,1144 @@
+use anyhow::{bail, Context, Result};
+use selectel_mks::nodegroup;
+use selectel_mks::Client;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, IsTerminal};
+
+use crate::api;
+use crate::cluster;
+use crate::confirm;
+use crate::logging;
+use crate::manifest;
+use crate::output;
+use crate::region;
+use crate::wait;
+
+/// A single change needed to bring the cluster to the state described by its manifest.
+#[derive(Debug, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub(crate) enum Change {
+    /// Create the cluster with all of its nodegroups.
+    CreateCluster {
+        name: String,
+        region: String,
+        nodegroups: usize,
+    },
+
+    /// Update parameters of the existing cluster.
+    UpdateCluster {
+        #[serde(skip)]
+        opts: api::UpdateClusterOpts,
+        fields: Vec<FieldChange>,
+    },
+
+    /// Create a nodegroup from the manifest nodegroup with the index.
+    CreateNodegroup {
+        #[serde(skip)]
+        index: usize,
+        availability_zone: String,
+        nodes_count: u32,
+    },
+
+    /// Change nodes count of the nodegroup.
+    ResizeNodegroup {
+        nodegroup_id: String,
+        from: u32,
+        to: u32,
+    },
+
+    /// Replace Kubernetes labels of the nodegroup.
+    UpdateNodegroupLabels {
+        nodegroup_id: String,
+        from: BTreeMap<String, String>,
+        to: BTreeMap<String, String>,
+    },
+
+    /// Delete the nodegroup that is not described in the manifest.
+    DeleteNodegroup {
+        nodegroup_id: String,
+        availability_zone: String,
+        flavor_id: String,
+        nodes_count: u32,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::CreateCluster {
+                name,
+                region,
+                nodegroups,
+            } => write!(
+                f,
+                "create cluster {} in {} with {} nodegroup(s)",
+                name, region, nodegroups
+            ),
+            Change::UpdateCluster { .. } => write!(f, "update cluster parameters"),
+            Change::CreateNodegroup {
+                availability_zone,
+                nodes_count,
+                ..
+            } => write!(
+                f,
+                "create nodegroup in {} with {} node(s)",
+                availability_zone, nodes_count
+            ),
+            Change::ResizeNodegroup {
+                nodegroup_id,
+                from,
+                to,
+            } => write!(
+                f,
+                "resize nodegroup {} from {} to {} node(s)",
+                nodegroup_id, from, to
+            ),
+            Change::UpdateNodegroupLabels { nodegroup_id, .. } => {
+                write!(f, "update labels of nodegroup {}", nodegroup_id)
+            }
+            Change::DeleteNodegroup { nodegroup_id, .. } => {
+                write!(f, "delete nodegroup {}", nodegroup_id)
+            }
+        }
+    }
+}
+
+/// Difference of a single field between the existing resource and its manifest.
+#[derive(Debug, Serialize)]
+pub(crate) struct FieldChange {
+    pub(crate) field: String,
+    pub(crate) from: serde_json::Value,
+    pub(crate) to: serde_json::Value,
+}
+
+impl FieldChange {
+    fn new<T: Serialize>(field: &str, from: T, to: T) -> FieldChange {
+        FieldChange {
+            field: String::from(field),
+            from: serde_json::to_value(from).unwrap_or_default(),
+            to: serde_json::to_value(to).unwrap_or_default(),
+        }
+    }
+}
+
+/// Difference that can't be applied since the MKS API doesn't allow to change the field.
+#[derive(Debug, Serialize)]
+pub(crate) struct Unsupported {
+    /// Changed resource, e.g. "cluster prod" or "nodegroup <id>".
+    pub(crate) resource: String,
+
+    #[serde(flatten)]
+    pub(crate) change: FieldChange,
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} = {} -> {}",
+            self.resource, self.change.field, self.change.from, self.change.to
+        )
+    }
+}
+
+/// Changes needed to apply the manifest.
+#[derive(Debug, Serialize)]
+pub(crate) struct Plan {
+    /// Name of the cluster from the manifest.
+    pub(crate) cluster: String,
+
+    /// Identifier of the existing cluster, it's empty when the cluster has to be created.
+    pub(crate) cluster_id: Option<String>,
+
+    pub(crate) changes: Vec<Change>,
+
+    /// Differences that can't be applied, the resources have to be recreated to change them.
+    pub(crate) unsupported: Vec<Unsupported>,
+
+    /// Count of existing nodegroups that are not described in the manifest and are kept
+    /// since pruning is disabled.
+    pub(crate) unmanaged_nodegroups: usize,
+}
+
+/// Count of created, changed and deleted resources.
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct Summary {
+    pub(crate) add: usize,
+    pub(crate) change: usize,
+    pub(crate) destroy: usize,
+}
+
+impl Plan {
+    pub(crate) fn summary(&self) -> Summary {
+        let mut summary = Summary::default();
+        for change in self.changes.iter() {
+            match change {
+                Change::CreateCluster { nodegroups, .. } => summary.add += 1 + nodegroups,
+                Change::CreateNodegroup { .. } => summary.add += 1,
+                Change::UpdateCluster { .. }
+                | Change::ResizeNodegroup { .. }
+                | Change::UpdateNodegroupLabels { .. } => summary.change += 1,
+                Change::DeleteNodegroup { .. } => summary.destroy += 1,
+            }
+        }
+
+        summary
+    }
+}
+
+/// Find the cluster described by the manifest and compare them.
+pub(crate) fn plan(
+    client: &Client,
+    spec: &manifest::ClusterSpec,
+    endpoint_region: Option<&str>,
+    prune: bool,
+) -> Result<Plan> {
+    let region = match (&spec.region, endpoint_region) {
+        (Some(region), endpoint_region) => {
+            region::check(region, endpoint_region)?;
+            region.clone()
+        }
+        (None, Some(endpoint_region)) => String::from(endpoint_region),
+        (None, None) => bail!("Cluster region is not set in the manifest"),
+    };
+
+    let clusters = logging::call("list_clusters", "", || client.list_clusters())
+        .context("Failed to list clusters")?;
+    let mut found = clusters.iter().filter(|cluster| cluster.name == spec.name);
+    let existing = found.next();
+    if found.next().is_some() {
+        bail!("Found more than one cluster with name {}", spec.name);
+    }
+
+    let existing = match existing {
+        Some(existing) => existing,
+        None => {
+            return Ok(Plan {
+                cluster: spec.name.clone(),
+                cluster_id: None,
+                changes: vec![Change::CreateCluster {
+                    name: spec.name.clone(),
+                    region,
+                    nodegroups: spec.nodegroups.len(),
+                }],
+                unsupported: Vec::new(),
+                unmanaged_nodegroups: 0,
+            })
+        }
+    };
+
+    let nodegroups = logging::call(
+        "list_nodegroups",
+        &format!("cluster_id={}", existing.id),
+        || client.list_nodegroups(&existing.id),
+    )
+    .context("Failed to list nodegroups")?;
+
+    compare(spec, existing, &nodegroups, prune)
+}
+
+/// Compare the manifest with the existing cluster and its nodegroups.
+///
+/// Nodegroups that are not described in the manifest are deleted only if `prune` is set.
+fn compare(
+    spec: &manifest::ClusterSpec,
+    existing: &selectel_mks::cluster::schemas::Cluster,
+    nodegroups: &[nodegroup::schemas::Nodegroup],
+    prune: bool,
+) -> Result<Plan> {
+    let mut changes = Vec::new();
+    let mut unsupported = Vec::new();
+
+    let cluster_resource = format!("cluster {}", spec.name);
+    for change in immutable_cluster_changes(spec, existing) {
+        unsupported.push(Unsupported {
+            resource: cluster_resource.clone(),
+            change,
+        });
+    }
+    if let Some(change) = update_cluster(spec, existing) {
+        changes.push(change);
+    }
+
+    // Nodegroups are matched by their identifiers or parameters first, the rest of manifest
+    // nodegroups are paired with unmatched nodegroups in the same availability zone so a changed
+    // flavor or volume is reported instead of creating another nodegroup.
+    let mut matched = vec![false; nodegroups.len()];
+    let mut positions = Vec::with_capacity(spec.nodegroups.len());
+    for nodegroup_spec in spec.nodegroups.iter() {
+        let position = nodegroups
+            .iter()
+            .enumerate()
+            .position(|(i, nodegroup)| !matched[i] && nodegroup_spec.matches(nodegroup));
+        match position {
+            Some(position) => matched[position] = true,
+            None => {
+                if let Some(id) = &nodegroup_spec.id {
+                    bail!("Nodegroup {} is not found in cluster {}", id, spec.name);
+                }
+            }
+        }
+        positions.push(position);
+    }
+    for (nodegroup_spec, position) in spec.nodegroups.iter().zip(positions.iter_mut()) {
+        if position.is_some() {
+            continue;
+        }
+        *position = nodegroups.iter().enumerate().position(|(i, nodegroup)| {
+            !matched[i] && nodegroup.availability_zone == nodegroup_spec.availability_zone
+        });
+        if let Some(position) = *position {
+            matched[position] = true;
+        }
+    }
+
+    for (index, (nodegroup_spec, position)) in spec.nodegroups.iter().zip(positions).enumerate() {
+        let nodegroup = match position {
+            Some(position) => &nodegroups[position],
+            None => {
+                changes.push(Change::CreateNodegroup {
+                    index,
+                    availability_zone: nodegroup_spec.availability_zone.clone(),
+                    nodes_count: nodegroup_spec.nodes_count,
+                });
+                continue;
+            }
+        };
+
+        let nodegroup_resource = format!("nodegroup {}", nodegroup.id);
+        for change in immutable_nodegroup_changes(nodegroup_spec, nodegroup) {
+            unsupported.push(Unsupported {
+                resource: nodegroup_resource.clone(),
+                change,
+            });
+        }
+
+        let nodes_count = nodegroup.nodes.len() as u32;
+        if nodes_count != nodegroup_spec.nodes_count {
+            changes.push(Change::ResizeNodegroup {
+                nodegroup_id: nodegroup.id.clone(),
+                from: nodes_count,
+                to: nodegroup_spec.nodes_count,
+            });
+        }
+
+        if let Some(labels) = &nodegroup_spec.labels {
+            let existing_labels: BTreeMap<String, String> = nodegroup
+                .labels
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            if existing_labels != *labels {
+                changes.push(Change::UpdateNodegroupLabels {
+                    nodegroup_id: nodegroup.id.clone(),
+                    from: existing_labels,
+                    to: labels.clone(),
+                });
+            }
+        }
+    }
+
+    let mut unmanaged_nodegroups = 0;
+    for (nodegroup, matched) in nodegroups.iter().zip(matched) {
+        if matched {
+            continue;
+        }
+        if !prune {
+            unmanaged_nodegroups += 1;
+            continue;
+        }
+
+        changes.push(Change::DeleteNodegroup {
+            nodegroup_id: nodegroup.id.clone(),
+            availability_zone: nodegroup.availability_zone.clone(),
+            flavor_id: nodegroup.flavor_id.clone(),
+            nodes_count: nodegroup.nodes.len() as u32,
+        });
+    }
+
+    Ok(Plan {
+        cluster: spec.name.clone(),
+        cluster_id: Some(existing.id.clone()),
+        changes,
+        unsupported,
+        unmanaged_nodegroups,
+    })
+}
+
+/// Get changed cluster parameters that can be updated.
+fn update_cluster(
+    spec: &manifest::ClusterSpec,
+    existing: &selectel_mks::cluster::schemas::Cluster,
+) -> Option<Change> {
+    let mut opts = api::UpdateClusterOpts::default();
+    let mut fields = Vec::new();
+
+    if let Some(maintenance_window_start) = &spec.maintenance_window_start {
+        if existing.maintenance_window_start.as_ref() != Some(maintenance_window_start) {
+            fields.push(FieldChange::new(
+                "maintenance_window_start",
+                existing.maintenance_window_start.clone(),
+                Some(maintenance_window_start.clone()),
+            ));
+            opts.maintenance_window_start = Some(maintenance_window_start.clone());
+        }
+    }
+    if let Some(enable_autorepair) = spec.enable_autorepair {
+        if existing.enable_autorepair != enable_autorepair {
+            fields.push(FieldChange::new(
+                "enable_autorepair",
+                existing.enable_autorepair,
+                enable_autorepair,
+            ));
+            opts.enable_autorepair = Some(enable_autorepair);
+        }
+    }
+    if let Some(enable_patch_version_auto_upgrade) = spec.enable_patch_version_auto_upgrade {
+        if existing.enable_patch_version_auto_upgrade != enable_patch_version_auto_upgrade {
+            fields.push(FieldChange::new(
+                "enable_patch_version_auto_upgrade",
+                existing.enable_patch_version_auto_upgrade,
+                enable_patch_version_auto_upgrade,
+            ));
+            opts.enable_patch_version_auto_upgrade = Some(enable_patch_version_auto_upgrade);
+        }
+    }
+
+    if fields.is_empty() {
+        None
+    } else {
+        Some(Change::UpdateCluster { opts, fields })
+    }
+}
+
+/// Get changed cluster parameters that the MKS API doesn't allow to update.
+fn immutable_cluster_changes(
+    spec: &manifest::ClusterSpec,
+    existing: &selectel_mks::cluster::schemas::Cluster,
+) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+
+    if spec.kube_version != existing.kube_version {
+        changes.push(FieldChange::new(
+            "kube_version",
+            &existing.kube_version,
+            &spec.kube_version,
+        ));
+    }
+    if let Some(zonal) = spec.zonal {
+        if zonal != existing.zonal {
+            changes.push(FieldChange::new("zonal", existing.zonal, zonal));
+        }
+    }
+    if let Some(network_id) = &spec.network_id {
+        if *network_id != existing.network_id {
+            changes.push(FieldChange::new(
+                "network_id",
+                &existing.network_id,
+                network_id,
+            ));
+        }
+    }
+    if let Some(subnet_id) = &spec.subnet_id {
+        if *subnet_id != existing.subnet_id {
+            changes.push(FieldChange::new(
+                "subnet_id",
+                &existing.subnet_id,
+                subnet_id,
+            ));
+        }
+    }
+
+    changes
+}
+
+/// Get changed nodegroup parameters that the MKS API doesn't allow to update.
+fn immutable_nodegroup_changes(
+    spec: &manifest::NodegroupSpec,
+    existing: &nodegroup::schemas::Nodegroup,
+) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+
+    if spec.local_volume != existing.local_volume {
+        changes.push(FieldChange::new(
+            "local_volume",
+            existing.local_volume,
+            spec.local_volume,
+        ));
+    }
+    if let Some(flavor_id) = &spec.flavor_id {
+        if *flavor_id != existing.flavor_id {
+            changes.push(FieldChange::new(
+                "flavor_id",
+                &existing.flavor_id,
+                flavor_id,
+            ));
+        }
+    }
+    if let Some(volume_gb) = spec.volume_gb {
+        if volume_gb != existing.volume_gb {
+            changes.push(FieldChange::new("volume_gb", existing.volume_gb, volume_gb));
+        }
+    }
+    if let Some(volume_type) = &spec.volume_type {
+        if *volume_type != existing.volume_type {
+            changes.push(FieldChange::new(
+                "volume_type",
+                &existing.volume_type,
+                volume_type,
+            ));
+        }
+    }
+
+    changes
+}
+
+/// Create missing resources and update existing ones according to the manifest.
+pub(crate) fn apply(
+    client: &Client,
+    api_client: &api::Client,
+    filename: &str,
+    endpoint_region: Option<&str>,
+    prune: bool,
+    yes: bool,
+    wait_opts: &wait::WaitOpts,
+) -> Result<()> {
+    let spec = manifest::load(filename)?;
+    let plan = plan(client, &spec, endpoint_region, prune)?;
+
+    for unsupported in plan.unsupported.iter() {
+        eprintln!(
+            "Warning: {} can't be changed, recreate the resource to change it",
+            unsupported
+        );
+    }
+
+    if plan.changes.is_empty() {
+        println!("No changes, cluster {} is up to date", spec.name);
+        return Ok(());
+    }
+
+    let deleted: Vec<&Change> = plan
+        .changes
+        .iter()
+        .filter(|change| matches!(change, Change::DeleteNodegroup { .. }))
+        .collect();
+    if !deleted.is_empty() && !yes {
+        eprintln!(
+            "Nodegroups of cluster {} that are not described in the manifest:",
+            spec.name
+        );
+        for change in deleted.iter() {
+            if let Change::DeleteNodegroup {
+                nodegroup_id,
+                availability_zone,
+                flavor_id,
+                nodes_count,
+            } = change
+            {
+                eprintln!(
+                    "  {} in {} with flavor {} and {} node(s)",
+                    nodegroup_id, availability_zone, flavor_id, nodes_count
+                );
+            }
+        }
+        if !confirm::confirm(&format!("Delete {} nodegroup(s)?", deleted.len()), None)? {
+            bail!("Apply is cancelled");
+        }
+    }
+
+    let mut cluster_id = plan.cluster_id.clone();
+    for change in plan.changes.iter() {
+        // The MKS API doesn't accept changes while the cluster is being updated.
+        if let Some(cluster_id) = &cluster_id {
+            cluster::wait_active(client, cluster_id, wait_opts)?;
+        }
+
+        match change {
+            Change::CreateCluster { region, .. } => {
+                let opts = spec.create_opts(region);
+                logging::dump("create_cluster request", &opts);
+                let created = logging::call("create_cluster", "", || client.create_cluster(&opts))
+                    .context("Failed to create cluster")?;
+                cluster_id = Some(created.id);
+            }
+            Change::UpdateCluster { opts, .. } => {
+                let cluster_id = cluster_id.as_deref().unwrap_or_default();
+                logging::dump("update_cluster request", opts);
+                logging::call(
+                    "update_cluster",
+                    &format!("cluster_id={}", cluster_id),
+                    || api_client.update_cluster(cluster_id, opts),
+                )
+                .context("Failed to update cluster")?;
+            }
+            Change::CreateNodegroup { index, .. } => {
+                let cluster_id = cluster_id.as_deref().unwrap_or_default();
+                let opts = spec.nodegroups[*index].create_opts();
+                logging::dump("create_nodegroup request", &opts);
+                logging::call(
+                    "create_nodegroup",
+                    &format!("cluster_id={}", cluster_id),
+                    || client.create_nodegroup(cluster_id, &opts),
+                )
+                .context("Failed to create nodegroup")?;
+            }
+            Change::ResizeNodegroup {
+                nodegroup_id, to, ..
+            } => {
+                let cluster_id = cluster_id.as_deref().unwrap_or_default();
+                let opts = nodegroup::schemas::ResizeOpts::new(*to);
+                logging::dump("resize_nodegroup request", &opts);
+                logging::call(
+                    "resize_nodegroup",
+                    &format!("cluster_id={} nodegroup_id={}", cluster_id, nodegroup_id),
+                    || client.resize_nodegroup(cluster_id, nodegroup_id, &opts),
+                )
+                .context("Failed to resize nodegroup")?;
+            }
+            Change::UpdateNodegroupLabels {
+                nodegroup_id, to, ..
+            } => {
+                let cluster_id = cluster_id.as_deref().unwrap_or_default();
+                let opts = nodegroup::schemas::UpdateOpts::new().with_labels(manifest::labels(to));
+                logging::dump("update_nodegroup request", &opts);
+                logging::call(
+                    "update_nodegroup",
+                    &format!("cluster_id={} nodegroup_id={}", cluster_id, nodegroup_id),
+                    || client.update_nodegroup(cluster_id, nodegroup_id, &opts),
+                )
+                .context("Failed to update nodegroup")?;
+            }
+            Change::DeleteNodegroup { nodegroup_id, .. } => {
+                let cluster_id = cluster_id.as_deref().unwrap_or_default();
+                logging::call(
+                    "delete_nodegroup",
+                    &format!("cluster_id={} nodegroup_id={}", cluster_id, nodegroup_id),
+                    || client.delete_nodegroup(cluster_id, nodegroup_id),
+                )
+                .context("Failed to delete nodegroup")?;
+            }
+        };
+
+        println!("{}: done", change);
+    }
+
+    if let Some(cluster_id) = &cluster_id {
+        cluster::wait_active(client, cluster_id, wait_opts)?;
+    }
+
+    println!(
+        "Applied {} change(s) to cluster {}",
+        plan.changes.len(),
+        spec.name
+    );
+
+    Ok(())
+}
+
+/// Print changes that apply would make without making them.
+pub(crate) fn diff(
+    client: &Client,
+    format: &output::Format,
+    filename: &str,
+    endpoint_region: Option<&str>,
+    prune: bool,
+) -> Result<()> {
+    let spec = manifest::load(filename)?;
+    let plan = plan(client, &spec, endpoint_region, prune)?;
+
+    output::print(
+        format,
+        &DiffReport {
+            spec: &spec,
+            summary: plan.summary(),
+            plan,
+        },
+    )
+}
+
+#[derive(Debug, Serialize)]
+struct DiffReport<'a> {
+    #[serde(skip)]
+    spec: &'a manifest::ClusterSpec,
+
+    #[serde(flatten)]
+    plan: Plan,
+
+    summary: Summary,
+}
+
+impl output::Render for DiffReport<'_> {
+    fn print_table(&self) {
+        print!("{}", render(self.spec, &self.plan, Style::detect()));
+    }
+}
+
+/// ANSI colors of the plan, they're used only for terminals.
+#[derive(Debug, Clone, Copy)]
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    fn detect() -> Style {
+        Style {
+            color: io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+
+    fn paint(self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            String::from(text)
+        }
+    }
+}
+
+const GREEN: &str = "32";
+const YELLOW: &str = "33";
+const RED: &str = "31";
+
+/// Render the plan in the Terraform-like format.
+fn render(spec: &manifest::ClusterSpec, plan: &Plan, style: Style) -> String {
+    let mut out = String::new();
+
+    if plan.changes.is_empty() {
+        out.push_str(&format!(
+            "No changes, cluster {} is up to date.\n",
+            plan.cluster
+        ));
+        render_unsupported(&mut out, plan);
+        render_unmanaged(&mut out, plan);
+        return out;
+    }
+
+    match &plan.cluster_id {
+        Some(cluster_id) => {
+            out.push_str(&format!("Cluster {} ({}):\n\n", plan.cluster, cluster_id))
+        }
+        None => out.push_str(&format!("Cluster {}:\n\n", plan.cluster)),
+    }
+
+    for change in plan.changes.iter() {
+        match change {
+            Change::CreateCluster { region, .. } => {
+                let mut attributes = attributes(spec);
+                attributes.retain(|(key, _)| !key.starts_with("nodegroups"));
+                attributes.retain(|(key, _)| key != "region");
+                attributes.push((String::from("region"), format!("{:?}", region)));
+                render_block(
+                    &mut out,
+                    style.paint(GREEN, "+"),
+                    &format!("cluster {}", spec.name),
+                    attributes
+                        .into_iter()
+                        .map(|(key, value)| (key, value, None))
+                        .collect(),
+                );
+                for nodegroup_spec in spec.nodegroups.iter() {
+                    render_created_nodegroup(&mut out, nodegroup_spec, style);
+                }
+            }
+            Change::UpdateCluster { fields, .. } => render_block(
+                &mut out,
+                style.paint(YELLOW, "~"),
+                &format!("cluster {}", spec.name),
+                fields
+                    .iter()
+                    .map(|field| {
+                        (
+                            field.field.clone(),
+                            field.from.to_string(),
+                            Some(field.to.to_string()),
+                        )
+                    })
+                    .collect(),
+            ),
+            Change::CreateNodegroup { index, .. } => {
+                render_created_nodegroup(&mut out, &spec.nodegroups[*index], style)
+            }
+            Change::ResizeNodegroup {
+                nodegroup_id,
+                from,
+                to,
+            } => render_block(
+                &mut out,
+                style.paint(YELLOW, "~"),
+                &format!("nodegroup {}", nodegroup_id),
+                vec![(
+                    String::from("nodes_count"),
+                    from.to_string(),
+                    Some(to.to_string()),
+                )],
+            ),
+            Change::UpdateNodegroupLabels {
+                nodegroup_id,
+                from,
+                to,
+            } => {
+                let mut keys: Vec<&String> = from.keys().chain(to.keys()).collect();
+                keys.sort();
+                keys.dedup();
+
+                let changed = keys
+                    .into_iter()
+                    .filter(|key| from.get(*key) != to.get(*key))
+                    .map(|key| {
+                        (
+                            format!("labels.{}", key),
+                            label(from.get(key)),
+                            Some(label(to.get(key))),
+                        )
+                    })
+                    .collect();
+
+                render_block(
+                    &mut out,
+                    style.paint(YELLOW, "~"),
+                    &format!("nodegroup {}", nodegroup_id),
+                    changed,
+                )
+            }
+            Change::DeleteNodegroup {
+                nodegroup_id,
+                availability_zone,
+                flavor_id,
+                nodes_count,
+            } => render_block(
+                &mut out,
+                style.paint(RED, "-"),
+                &format!("nodegroup {}", nodegroup_id),
+                vec![
+                    (
+                        String::from("availability_zone"),
+                        format!("{:?}", availability_zone),
+                        None,
+                    ),
+                    (String::from("flavor_id"), format!("{:?}", flavor_id), None),
+                    (String::from("nodes_count"), nodes_count.to_string(), None),
+                ],
+            ),
+        }
+    }
+
+    let summary = plan.summary();
+    out.push_str(&format!(
+        "Plan: {} to add, {} to change, {} to destroy.\n",
+        style.paint(GREEN, &summary.add.to_string()),
+        style.paint(YELLOW, &summary.change.to_string()),
+        style.paint(RED, &summary.destroy.to_string()),
+    ));
+    render_unsupported(&mut out, plan);
+    render_unmanaged(&mut out, plan);
+
+    out
+}
+
+fn render_unsupported(out: &mut String, plan: &Plan) {
+    if plan.unsupported.is_empty() {
+        return;
+    }
+
+    out.push_str(
+        "The following differences can't be applied, recreate the resources to change them:\n",
+    );
+    for unsupported in plan.unsupported.iter() {
+        out.push_str(&format!("  {}\n", unsupported));
+    }
+}
+
+fn render_unmanaged(out: &mut String, plan: &Plan) {
+    if plan.unmanaged_nodegroups > 0 {
+        out.push_str(&format!(
+            "{} existing nodegroup(s) are not described in the manifest, use --prune to delete them.\n",
+            plan.unmanaged_nodegroups
+        ));
+    }
+}
+
+fn render_created_nodegroup(
+    out: &mut String,
+    nodegroup_spec: &manifest::NodegroupSpec,
+    style: Style,
+) {
+    render_block(
+        out,
+        style.paint(GREEN, "+"),
+        &format!("nodegroup in {}", nodegroup_spec.availability_zone),
+        attributes(nodegroup_spec)
+            .into_iter()
+            .map(|(key, value)| (key, value, None))
+            .collect(),
+    );
+}
+
+/// Render a changed resource with its attributes, changed attributes contain their new values.
+fn render_block(
+    out: &mut String,
+    sign: String,
+    title: &str,
+    attributes: Vec<(String, String, Option<String>)>,
+) {
+    out.push_str(&format!("  {} {}\n", sign, title));
+
+    let width = attributes
+        .iter()
+        .map(|(key, _, _)| key.len())
+        .max()
+        .unwrap_or_default();
+    for (key, value, new_value) in attributes.iter() {
+        match new_value {
+            Some(new_value) => out.push_str(&format!(
+                "      {:width$} = {} -> {}\n",
+                key,
+                value,
+                new_value,
+                width = width
+            )),
+            None => out.push_str(&format!(
+                "      {:width$} = {}\n",
+                key,
+                value,
+                width = width
+            )),
+        }
+    }
+
+    out.push('\n');
+}
+
+/// Get flattened attributes of the manifest object.
+fn attributes<T: Serialize>(spec: &T) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    if let Ok(value) = serde_json::to_value(spec) {
+        flatten(&mut attributes, "", &value);
+    }
+
+    attributes
+}
+
+fn flatten(attributes: &mut Vec<(String, String)>, prefix: &str, value: &serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            for (key, value) in object.iter() {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(attributes, &key, value);
+            }
+        }
+        serde_json::Value::Array(_) => {}
+        serde_json::Value::Null => {}
+        value => attributes.push((String::from(prefix), value.to_string())),
+    }
+}
+
+fn label(value: Option<&String>) -> String {
+    match value {
+        Some(value) => format!("{:?}", value),
+        None => String::from("(none)"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cluster() -> selectel_mks::cluster::schemas::Cluster {
+        serde_json::from_value(json!({
+            "id": "c1",
+            "created_at": "2021-01-01T00:00:00Z",
+            "updated_at": null,
+            "name": "prod",
+            "status": "ACTIVE",
+            "project_id": "p1",
+            "network_id": "net1",
+            "subnet_id": "sub1",
+            "kube_api_ip": "10.0.0.1",
+            "kube_version": "1.20.4",
+            "region": "ru-3",
+            "pki_tree_updated_at": null,
+            "maintenance_window_start": "01:00:00",
+            "maintenance_window_end": "03:00:00",
+            "maintenance_last_start": null,
+            "enable_autorepair": true,
+            "enable_patch_version_auto_upgrade": false,
+            "zonal": false,
+            "kubernetes_options": {"enable_pod_security_policy": true},
+        }))
+        .unwrap()
+    }
+
+    fn nodegroup(
+        id: &str,
+        availability_zone: &str,
+        flavor_id: &str,
+        count: usize,
+    ) -> nodegroup::schemas::Nodegroup {
+        let nodes: Vec<serde_json::Value> = (0..count)
+            .map(|i| {
+                json!({
+                    "id": format!("{}-{}", id, i),
+                    "created_at": "2021-01-01T00:00:00Z",
+                    "updated_at": null,
+                    "hostname": format!("{}-{}", id, i),
+                    "ip": "10.0.0.10",
+                    "nodegroup_id": id,
+                })
+            })
+            .collect();
+
+        serde_json::from_value(json!({
+            "id": id,
+            "created_at": "2021-01-01T00:00:00Z",
+            "updated_at": null,
+            "cluster_id": "c1",
+            "flavor_id": flavor_id,
+            "volume_gb": 32,
+            "volume_type": "fast.ru-3a",
+            "local_volume": false,
+            "availability_zone": availability_zone,
+            "nodes": nodes,
+            "labels": {"role": "web"},
+        }))
+        .unwrap()
+    }
+
+    fn spec(nodegroups: &str) -> manifest::ClusterSpec {
+        serde_yaml::from_str(&format!(
+            "name: prod\nkube_version: 1.20.4\nnodegroups:\n{}",
+            nodegroups
+        ))
+        .unwrap()
+    }
+
+    fn changes(plan: &Plan) -> Vec<String> {
+        plan.changes
+            .iter()
+            .map(|change| change.to_string())
+            .collect()
+    }
+
+    fn unsupported(plan: &Plan) -> Vec<String> {
+        plan.unsupported
+            .iter()
+            .map(|change| change.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn match_by_id() {
+        let nodegroups = vec![
+            nodegroup("ng1", "ru-3a", "f1", 2),
+            nodegroup("ng2", "ru-3a", "f1", 2),
+        ];
+        let manifest = spec("- {id: ng2, availability_zone: ru-3a, flavor_id: f2, nodes_count: 3}");
+
+        let plan = compare(&manifest, &cluster(), &nodegroups, false).unwrap();
+        assert_eq!(
+            changes(&plan),
+            vec!["resize nodegroup ng2 from 2 to 3 node(s)"]
+        );
+        assert_eq!(
+            unsupported(&plan),
+            vec![r#"nodegroup ng2: flavor_id = "f1" -> "f2""#]
+        );
+        assert_eq!(plan.unmanaged_nodegroups, 1);
+
+        let manifest = spec("- {id: ng3, availability_zone: ru-3a, nodes_count: 2}");
+        let err = compare(&manifest, &cluster(), &nodegroups, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Nodegroup ng3 is not found in cluster prod"
+        );
+    }
+
+    #[test]
+    fn match_by_parameters() {
+        let nodegroups = vec![
+            nodegroup("ng1", "ru-3a", "f1", 2),
+            nodegroup("ng2", "ru-3a", "f2", 2),
+        ];
+        let manifest =
+            spec("- {availability_zone: ru-3a, flavor_id: f2, nodes_count: 2, labels: {role: db}}");
+
+        let plan = compare(&manifest, &cluster(), &nodegroups, false).unwrap();
+        assert_eq!(changes(&plan), vec!["update labels of nodegroup ng2"]);
+        assert!(plan.unsupported.is_empty());
+        assert_eq!(plan.unmanaged_nodegroups, 1);
+    }
+
+    #[test]
+    fn pair_by_availability_zone() {
+        let nodegroups = vec![
+            nodegroup("ng1", "ru-3a", "f1", 2),
+            nodegroup("ng2", "ru-3b", "f1", 2),
+        ];
+        // The first manifest nodegroup matches ng1 by its parameters, so the second one is paired
+        // with ng2 in its zone instead of creating another nodegroup.
+        let manifest = spec(
+            "- {availability_zone: ru-3b, flavor_id: f2, nodes_count: 2}\n\
+             - {availability_zone: ru-3a, flavor_id: f1, nodes_count: 2}\n\
+             - {availability_zone: ru-3c, flavor_id: f1, nodes_count: 1}",
+        );
+
+        let plan = compare(&manifest, &cluster(), &nodegroups, false).unwrap();
+        assert_eq!(
+            changes(&plan),
+            vec!["create nodegroup in ru-3c with 1 node(s)"]
+        );
+        assert_eq!(
+            unsupported(&plan),
+            vec![r#"nodegroup ng2: flavor_id = "f1" -> "f2""#]
+        );
+        assert_eq!(plan.unmanaged_nodegroups, 0);
+    }
+
+    #[test]
+    fn prune() {
+        let nodegroups = vec![
+            nodegroup("ng1", "ru-3a", "f1", 2),
+            nodegroup("ng2", "ru-3b", "f1", 1),
+        ];
+        let manifest = spec("- {availability_zone: ru-3a, nodes_count: 2}");
+
+        let plan = compare(&manifest, &cluster(), &nodegroups, false).unwrap();
+        assert!(plan.changes.is_empty());
+        assert_eq!(plan.unmanaged_nodegroups, 1);
+
+        let plan = compare(&manifest, &cluster(), &nodegroups, true).unwrap();
+        assert_eq!(changes(&plan), vec!["delete nodegroup ng2"]);
+        assert_eq!(plan.unmanaged_nodegroups, 0);
+        assert_eq!(plan.summary().destroy, 1);
+    }
+
+    #[test]
+    fn cluster_changes() {
+        let mut manifest = spec("[]");
+        manifest.kube_version = String::from("1.21.1");
+        manifest.maintenance_window_start = Some(String::from("02:00:00"));
+        manifest.enable_autorepair = Some(true);
+
+        let plan = compare(&manifest, &cluster(), &[], false).unwrap();
+        assert_eq!(changes(&plan), vec!["update cluster parameters"]);
+        match &plan.changes[0] {
+            Change::UpdateCluster { opts, fields } => {
+                assert_eq!(fields.len(), 1);
+                assert_eq!(opts.maintenance_window_start.as_deref(), Some("02:00:00"));
+                assert_eq!(opts.enable_autorepair, None);
+            }
+            change => panic!("unexpected change {}", change),
+        }
+        assert_eq!(
+            unsupported(&plan),
+            vec![r#"cluster prod: kube_version = "1.20.4" -> "1.21.1""#]
+        );
+    }
+}
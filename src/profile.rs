@@ -0,0 +1,467 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use term_table::row::Row;
+use term_table::table_cell::{Alignment, TableCell};
+use term_table::{Table, TableStyle};
+
+use crate::region;
+
+/// CLI configuration file with named profiles.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct ConfigFile {
+    /// Name of the profile that is used when `--profile` is not provided.
+    pub(crate) current_profile: Option<String>,
+
+    #[serde(default)]
+    pub(crate) profiles: BTreeMap<String, Profile>,
+
+    /// MKS endpoints by region, they override the built-in ones.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) regions: BTreeMap<String, String>,
+}
+
+/// Endpoint and credentials of a single MKS project.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct Profile {
+    pub(crate) endpoint: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) token: Option<String>,
+
+    /// Shell command that prints the token to stdout.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) token_command: Option<String>,
+}
+
+impl Profile {
+    /// Get the profile token, running its token command if needed.
+    pub(crate) fn token(&self) -> Result<String> {
+        if let Some(token) = &self.token {
+            return Ok(token.clone());
+        }
+
+        let command = match &self.token_command {
+            Some(command) => command,
+            None => bail!("Profile has neither token nor token_command"),
+        };
+
+        let output = shell(command)
+            .output()
+            .with_context(|| format!("Failed to run token command \"{}\"", command))?;
+        if !output.status.success() {
+            bail!(
+                "Token command \"{}\" failed with {}: {}",
+                command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if token.is_empty() {
+            bail!("Token command \"{}\" printed an empty token", command);
+        }
+
+        Ok(token)
+    }
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command);
+    cmd
+}
+
+impl ConfigFile {
+    /// Read the configuration file, a missing file is treated as an empty configuration.
+    pub(crate) fn load(path: &Path) -> Result<ConfigFile> {
+        if !path.exists() {
+            return Ok(ConfigFile::default());
+        }
+
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+
+        toml::from_str(&raw)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+
+    /// Write the configuration file, it's only readable by the current user since it contains
+    /// tokens.
+    pub(crate) fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+        }
+
+        let serialized = toml::to_string(self).context("Failed to serialize config file")?;
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options
+            .open(path)
+            .with_context(|| format!("Failed to open config file {}", path.display()))?;
+        file.write_all(serialized.as_bytes())
+            .with_context(|| format!("Failed to write config file {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Get a profile by its name or the current profile if the name is not provided.
+    pub(crate) fn profile(&self, name: Option<&str>) -> Result<Option<&Profile>> {
+        let name = match name.or(self.current_profile.as_deref()) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+
+        self.profiles
+            .get(name)
+            .map(Some)
+            .ok_or_else(|| anyhow!("Profile \"{}\" is not found", name))
+    }
+}
+
+/// Get the configuration file path, `mks/config.toml` in the user configuration directory by
+/// default.
+pub(crate) fn config_path(path: Option<&str>) -> Result<PathBuf> {
+    if let Some(path) = path {
+        return Ok(PathBuf::from(path));
+    }
+
+    let dir = dirs::config_dir().context("Failed to find user configuration directory")?;
+
+    Ok(dir.join("mks").join("config.toml"))
+}
+
+/// MKS endpoint and token resolved from options and configuration file.
+#[derive(Debug)]
+pub(crate) struct Credentials {
+    pub(crate) endpoint: String,
+    pub(crate) token: String,
+
+    /// Region of the endpoint, it's unknown for custom endpoints.
+    pub(crate) region: Option<String>,
+}
+
+/// Resolve MKS endpoint and token.
+///
+/// Values provided with options or environment variables take precedence over the profile,
+/// an explicit endpoint takes precedence over the region. The profile is resolved only when
+/// some of the values are missing, otherwise the configuration file is used only for region
+/// overrides and errors of reading it are ignored.
+pub(crate) fn credentials(
+    path: &Path,
+    profile: Option<&str>,
+    mks_endpoint: Option<String>,
+    mks_region: Option<String>,
+    mks_token: Option<String>,
+) -> Result<Credentials> {
+    let explicit = mks_token.is_some() && (mks_endpoint.is_some() || mks_region.is_some());
+    let config = if explicit {
+        ConfigFile::load(path).unwrap_or_default()
+    } else {
+        ConfigFile::load(path)?
+    };
+
+    resolve(&config, profile, mks_endpoint, mks_region, mks_token)
+}
+
+/// Resolve MKS endpoint and token with the loaded configuration file.
+fn resolve(
+    config: &ConfigFile,
+    profile: Option<&str>,
+    mks_endpoint: Option<String>,
+    mks_region: Option<String>,
+    mks_token: Option<String>,
+) -> Result<Credentials> {
+    let explicit = mks_token.is_some() && (mks_endpoint.is_some() || mks_region.is_some());
+    let profile = if explicit {
+        None
+    } else {
+        config.profile(profile)?
+    };
+
+    let endpoint = match (mks_endpoint, &mks_region, profile) {
+        (Some(endpoint), _, _) => endpoint,
+        (None, Some(mks_region), _) => region::endpoint(mks_region, &config.regions)?,
+        (None, None, Some(profile)) => profile.endpoint.clone(),
+        (None, None, None) => bail!(
+            "MKS endpoint is not set, use --mks-endpoint, --region, their environment variables \
+            or a configuration profile"
+        ),
+    };
+
+    let endpoint_region = region::region(&endpoint, &config.regions);
+    if let Some(mks_region) = &mks_region {
+        region::check(mks_region, endpoint_region.as_deref())?;
+    }
+
+    let token = match (mks_token, profile) {
+        (Some(token), _) => token,
+        (None, Some(profile)) => profile.token()?,
+        (None, None) => {
+            bail!("MKS token is not set, use --mks-token, MKS_TOKEN or a configuration profile")
+        }
+    };
+
+    Ok(Credentials {
+        endpoint,
+        token,
+        region: endpoint_region.or(mks_region),
+    })
+}
+
+/// Resolve MKS endpoint and token of another region or project.
+///
+/// The source token is used when the profile is not provided since project-scoped tokens are valid
+/// in all regions.
+pub(crate) fn target_credentials(
+    path: &Path,
+    source: &Credentials,
+    profile: Option<&str>,
+    mks_region: Option<String>,
+) -> Result<Credentials> {
+    if profile.is_some() {
+        return credentials(path, profile, None, mks_region, None);
+    }
+
+    let mks_endpoint = if mks_region.is_none() {
+        Some(source.endpoint.clone())
+    } else {
+        None
+    };
+
+    credentials(
+        path,
+        None,
+        mks_endpoint,
+        mks_region,
+        Some(source.token.clone()),
+    )
+}
+
+pub(crate) fn add(
+    path: &Path,
+    name: &str,
+    endpoint: &str,
+    token: Option<String>,
+    token_command: Option<String>,
+    set_current: bool,
+) -> Result<()> {
+    if token.is_some() == token_command.is_some() {
+        bail!("Exactly one of --token or --token-command must be provided");
+    }
+
+    let mut config = ConfigFile::load(path)?;
+    if config.profiles.contains_key(name) {
+        bail!("Profile \"{}\" already exists", name);
+    }
+
+    config.profiles.insert(
+        String::from(name),
+        Profile {
+            endpoint: String::from(endpoint),
+            token,
+            token_command,
+        },
+    );
+    if set_current || config.current_profile.is_none() {
+        config.current_profile = Some(String::from(name));
+    }
+
+    config.save(path)
+}
+
+pub(crate) fn list(path: &Path) -> Result<()> {
+    let config = ConfigFile::load(path)?;
+
+    let mut table = Table::new();
+    table.style = TableStyle::simple();
+
+    table.add_row(Row::new(vec![
+        TableCell::new_with_alignment("current", 1, Alignment::Center),
+        TableCell::new_with_alignment("name", 1, Alignment::Center),
+        TableCell::new_with_alignment("endpoint", 1, Alignment::Center),
+        TableCell::new_with_alignment("token", 1, Alignment::Center),
+    ]));
+
+    for (name, profile) in config.profiles.iter() {
+        let current = if config.current_profile.as_deref() == Some(name) {
+            "*"
+        } else {
+            ""
+        };
+        let token = match &profile.token_command {
+            Some(command) => format!("command: {}", command),
+            None => String::from("stored"),
+        };
+
+        table.add_row(Row::new(vec![
+            TableCell::new(current),
+            TableCell::new(name),
+            TableCell::new(&profile.endpoint),
+            TableCell::new(token),
+        ]));
+    }
+
+    println!("{}", table.render());
+
+    Ok(())
+}
+
+pub(crate) fn remove(path: &Path, name: &str) -> Result<()> {
+    let mut config = ConfigFile::load(path)?;
+    if config.profiles.remove(name).is_none() {
+        bail!("Profile \"{}\" is not found", name);
+    }
+    if config.current_profile.as_deref() == Some(name) {
+        config.current_profile = None;
+    }
+
+    config.save(path)
+}
+
+pub(crate) fn use_profile(path: &Path, name: &str) -> Result<()> {
+    let mut config = ConfigFile::load(path)?;
+    if !config.profiles.contains_key(name) {
+        bail!("Profile \"{}\" is not found", name);
+    }
+    config.current_profile = Some(String::from(name));
+
+    config.save(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn config() -> ConfigFile {
+        toml::from_str(
+            r#"
+current_profile = "prod"
+
+[profiles.prod]
+endpoint = "https://ru-3.mks.selcloud.ru/v1"
+token = "prod-token"
+
+[profiles.local]
+endpoint = "http://127.0.0.1:8080/v1"
+token_command = "exit 1"
+
+[regions]
+local = "http://127.0.0.1:8080/v1"
+"#,
+        )
+        .unwrap()
+    }
+
+    fn some(value: &str) -> Option<String> {
+        Some(String::from(value))
+    }
+
+    #[test]
+    fn profile_values() {
+        let credentials = resolve(&config(), None, None, None, None).unwrap();
+        assert_eq!(credentials.endpoint, "https://ru-3.mks.selcloud.ru/v1");
+        assert_eq!(credentials.token, "prod-token");
+        assert_eq!(credentials.region.as_deref(), Some("ru-3"));
+
+        let err = resolve(&config(), Some("local"), None, None, None).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Token command \"exit 1\" failed"));
+    }
+
+    #[test]
+    fn endpoint_over_region() {
+        let credentials = resolve(
+            &config(),
+            None,
+            some("http://127.0.0.1:8080/v1/"),
+            some("local"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(credentials.endpoint, "http://127.0.0.1:8080/v1/");
+        assert_eq!(credentials.token, "prod-token");
+        assert_eq!(credentials.region.as_deref(), Some("local"));
+
+        let err = resolve(
+            &config(),
+            None,
+            some("https://ru-3.mks.selcloud.ru/v1"),
+            some("ru-7"),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Region \"ru-7\" doesn't match region \"ru-3\" of the MKS endpoint"
+        );
+    }
+
+    #[test]
+    fn region_over_profile() {
+        let credentials = resolve(&config(), None, None, some("ru-7"), None).unwrap();
+        assert_eq!(credentials.endpoint, "https://ru-7.mks.selcloud.ru/v1");
+        assert_eq!(credentials.token, "prod-token");
+        assert_eq!(credentials.region.as_deref(), Some("ru-7"));
+
+        let credentials = resolve(&config(), None, None, None, some("token")).unwrap();
+        assert_eq!(credentials.endpoint, "https://ru-3.mks.selcloud.ru/v1");
+        assert_eq!(credentials.token, "token");
+    }
+
+    #[test]
+    fn explicit_values_skip_profile() {
+        // The local profile fails to get its token, and the missing profile can't be found.
+        for profile in [Some("local"), Some("missing")].iter() {
+            let credentials =
+                resolve(&config(), *profile, None, some("local"), some("token")).unwrap();
+            assert_eq!(credentials.endpoint, "http://127.0.0.1:8080/v1");
+            assert_eq!(credentials.token, "token");
+        }
+
+        let err = resolve(&config(), Some("missing"), None, some("local"), None).unwrap_err();
+        assert_eq!(err.to_string(), "Profile \"missing\" is not found");
+
+        // The configuration file can't be read since it's a directory.
+        let dir = env::temp_dir();
+        let resolved =
+            credentials(&dir, None, some("http://localhost/v1"), None, some("token")).unwrap();
+        assert_eq!(resolved.endpoint, "http://localhost/v1");
+        assert_eq!(resolved.region, None);
+        assert!(credentials(&dir, None, some("http://localhost/v1"), None, None).is_err());
+    }
+
+    #[test]
+    fn missing_values() {
+        let empty = ConfigFile::default();
+
+        let err = resolve(&empty, None, None, None, some("token")).unwrap_err();
+        assert!(err.to_string().starts_with("MKS endpoint is not set"));
+
+        let err = resolve(&empty, None, some("http://localhost/v1"), None, None).unwrap_err();
+        assert!(err.to_string().starts_with("MKS token is not set"));
+    }
+}
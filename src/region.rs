@@ -0,0 +1,134 @@
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+
+// Built-in MKS endpoints for every region.
+const ENDPOINTS: &[(&str, &str)] = &[
+    ("ru-1", "https://ru-1.mks.selcloud.ru/v1"),
+    ("ru-2", "https://ru-2.mks.selcloud.ru/v1"),
+    ("ru-3", "https://ru-3.mks.selcloud.ru/v1"),
+    ("ru-7", "https://ru-7.mks.selcloud.ru/v1"),
+    ("ru-8", "https://ru-8.mks.selcloud.ru/v1"),
+];
+
+/// Get MKS endpoint of the region.
+///
+/// Regions from the configuration file take precedence over the built-in ones.
+pub(crate) fn endpoint(region: &str, overrides: &BTreeMap<String, String>) -> Result<String> {
+    if let Some(endpoint) = overrides.get(region) {
+        return Ok(endpoint.clone());
+    }
+
+    match ENDPOINTS.iter().find(|(name, _)| *name == region) {
+        Some((_, endpoint)) => Ok(String::from(*endpoint)),
+        None => bail!(
+            "Unknown region \"{}\", known regions are: {}",
+            region,
+            names(overrides).join(", ")
+        ),
+    }
+}
+
+/// Get region of the MKS endpoint, if it's known.
+pub(crate) fn region(endpoint: &str, overrides: &BTreeMap<String, String>) -> Option<String> {
+    let endpoint = endpoint.trim_end_matches('/');
+
+    overrides
+        .iter()
+        .map(|(name, endpoint)| (name.as_str(), endpoint.as_str()))
+        .chain(ENDPOINTS.iter().copied())
+        .find(|(_, known)| known.trim_end_matches('/') == endpoint)
+        .map(|(name, _)| String::from(name))
+}
+
+/// Check that the region matches the region of the MKS endpoint.
+pub(crate) fn check(region: &str, endpoint_region: Option<&str>) -> Result<()> {
+    match endpoint_region {
+        Some(endpoint_region) if endpoint_region != region => bail!(
+            "Region \"{}\" doesn't match region \"{}\" of the MKS endpoint",
+            region,
+            endpoint_region
+        ),
+        _ => Ok(()),
+    }
+}
+
+fn names(overrides: &BTreeMap<String, String>) -> Vec<&str> {
+    let mut names: Vec<&str> = ENDPOINTS.iter().map(|(name, _)| *name).collect();
+    for name in overrides.keys() {
+        if !names.contains(&name.as_str()) {
+            names.push(name);
+        }
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides() -> BTreeMap<String, String> {
+        let mut overrides = BTreeMap::new();
+        overrides.insert(
+            String::from("ru-7"),
+            String::from("https://mks.example.com/v1/"),
+        );
+        overrides.insert(String::from("local"), String::from("http://localhost/v1"));
+        overrides
+    }
+
+    #[test]
+    fn endpoints() {
+        let none = BTreeMap::new();
+        assert_eq!(
+            endpoint("ru-3", &none).unwrap(),
+            "https://ru-3.mks.selcloud.ru/v1"
+        );
+        assert_eq!(
+            endpoint("ru-7", &none).unwrap(),
+            "https://ru-7.mks.selcloud.ru/v1"
+        );
+        assert_eq!(
+            endpoint("ru-7", &overrides()).unwrap(),
+            "https://mks.example.com/v1/"
+        );
+        assert_eq!(
+            endpoint("local", &overrides()).unwrap(),
+            "http://localhost/v1"
+        );
+    }
+
+    #[test]
+    fn unknown_region() {
+        let err = endpoint("ru-9", &overrides()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown region \"ru-9\", known regions are: ru-1, ru-2, ru-3, ru-7, ru-8, local"
+        );
+    }
+
+    #[test]
+    fn regions() {
+        let none = BTreeMap::new();
+        assert_eq!(
+            region("https://ru-3.mks.selcloud.ru/v1/", &none).as_deref(),
+            Some("ru-3")
+        );
+        assert_eq!(
+            region("https://mks.example.com/v1", &overrides()).as_deref(),
+            Some("ru-7")
+        );
+        assert_eq!(
+            region("https://ru-7.mks.selcloud.ru/v1", &overrides()).as_deref(),
+            Some("ru-7")
+        );
+        assert_eq!(region("http://localhost:8080/v1", &overrides()), None);
+    }
+
+    #[test]
+    fn checks() {
+        assert!(check("ru-3", Some("ru-3")).is_ok());
+        assert!(check("ru-3", None).is_ok());
+        assert!(check("ru-3", Some("ru-7")).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::{bail, Context, Result};
+use selectel_mks::Client;
+
+use crate::logging;
+
+/// Get the cluster identifier by the identifier itself or by the unique cluster name.
+pub(crate) fn cluster(client: &Client, name_or_id: &str) -> Result<String> {
+    if is_uuid(name_or_id) {
+        return Ok(name_or_id.to_string());
+    }
+
+    let clusters = logging::call("list_clusters", "", || client.list_clusters())
+        .context("Failed to list clusters")?;
+    let found: Vec<_> = clusters
+        .iter()
+        .filter(|cluster| cluster.name == name_or_id)
+        .collect();
+
+    match found.as_slice() {
+        [] => bail!("Cluster \"{}\" is not found", name_or_id),
+        [cluster] => Ok(cluster.id.clone()),
+        _ => {
+            let candidates: Vec<String> = found
+                .iter()
+                .map(|cluster| {
+                    format!(
+                        "{} ({}, created at {})",
+                        cluster.id,
+                        cluster.status,
+                        cluster.created_at.to_rfc3339()
+                    )
+                })
+                .collect();
+            bail!(
+                "Cluster name \"{}\" is ambiguous, use one of the identifiers:\n  {}",
+                name_or_id,
+                candidates.join("\n  ")
+            )
+        }
+    }
+}
+
+/// Get the node identifier by the identifier itself or by the node hostname in the nodegroup.
+pub(crate) fn node(
+    client: &Client,
+    cluster_id: &str,
+    nodegroup_id: &str,
+    hostname_or_id: &str,
+) -> Result<String> {
+    if is_uuid(hostname_or_id) {
+        return Ok(hostname_or_id.to_string());
+    }
+
+    let nodegroup = logging::call(
+        "get_nodegroup",
+        &format!("cluster_id={} nodegroup_id={}", cluster_id, nodegroup_id),
+        || client.get_nodegroup(cluster_id, nodegroup_id),
+    )
+    .context("Failed to get nodegroup")?;
+    let found: Vec<_> = nodegroup
+        .nodes
+        .iter()
+        .filter(|node| node.hostname == hostname_or_id)
+        .collect();
+
+    match found.as_slice() {
+        [] => bail!(
+            "Node \"{}\" is not found in nodegroup {}",
+            hostname_or_id,
+            nodegroup_id
+        ),
+        [node] => Ok(node.id.clone()),
+        _ => {
+            let candidates: Vec<String> = found
+                .iter()
+                .map(|node| format!("{} ({})", node.id, node.ip))
+                .collect();
+            bail!(
+                "Node hostname \"{}\" is ambiguous, use one of the identifiers:\n  {}",
+                hostname_or_id,
+                candidates.join("\n  ")
+            )
+        }
+    }
+}
+
+/// Check if the value looks like an identifier, e.g. "d0c1e2f3-0000-4000-8000-0123456789ab".
+fn is_uuid(value: &str) -> bool {
+    value.len() == 36
+        && value.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
@@ -1,87 +1,369 @@
 use anyhow::{bail, Context, Result};
 use selectel_mks::task;
 use selectel_mks::Client;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, IsTerminal, Write};
 use term_table::row::Row;
 use term_table::table_cell::{Alignment, TableCell};
 use term_table::{Table, TableStyle};
 
-use crate::json;
+use crate::conf;
+use crate::logging;
+use crate::output;
+use crate::wait;
 
-pub(crate) fn get(client: &Client, output: &str, cluster_id: &str, task_id: &str) -> Result<()> {
-    let task = client
-        .get_task(cluster_id, task_id)
-        .context("Failed to get cluster task")?;
+pub(crate) fn get(
+    client: &Client,
+    format: &output::Format,
+    cluster_id: &str,
+    task_id: &str,
+) -> Result<()> {
+    let task = logging::call(
+        "get_task",
+        &format!("cluster_id={} task_id={}", cluster_id, task_id),
+        || client.get_task(cluster_id, task_id),
+    )
+    .context("Failed to get cluster task")?;
 
-    match output {
-        "table" => get_print_table(&task),
-        "json" => json::print_json(task)?,
-        _ => bail!("Unknown output format"),
-    };
+    output::print(format, &task)
+}
 
-    Ok(())
+impl output::Render for task::schemas::Task {
+    fn print_table(&self) {
+        let updated_at = match &self.updated_at {
+            Some(time) => time.to_rfc3339(),
+            None => String::new(),
+        };
+
+        let mut table = Table::new();
+        table.style = TableStyle::simple();
+        table.separate_rows = false;
+
+        table.add_row(Row::new(vec![
+            TableCell::new("id"),
+            TableCell::new(&self.id),
+        ]));
+        table.add_row(Row::new(vec![
+            TableCell::new("started_at"),
+            TableCell::new(self.started_at.to_rfc3339()),
+        ]));
+        table.add_row(Row::new(vec![
+            TableCell::new("updated_at"),
+            TableCell::new(updated_at),
+        ]));
+        table.add_row(Row::new(vec![
+            TableCell::new("type"),
+            TableCell::new(&self.task_type),
+        ]));
+        table.add_row(Row::new(vec![
+            TableCell::new("status"),
+            TableCell::new(&self.status),
+        ]));
+
+        println!("{}", table.render());
+    }
+
+    fn names(&self) -> Option<Vec<String>> {
+        Some(vec![format!("task/{}", self.id)])
+    }
 }
 
-fn get_print_table(task: &task::schemas::Task) {
-    let updated_at = match &task.updated_at {
-        Some(time) => time.to_rfc3339(),
-        None => String::new(),
-    };
+pub(crate) fn list(
+    client: &Client,
+    format: &output::Format,
+    table_opts: &output::TableOpts,
+    cluster_id: &str,
+) -> Result<()> {
+    let tasks = logging::call("list_tasks", &format!("cluster_id={}", cluster_id), || {
+        client.list_tasks(cluster_id)
+    })
+    .context("Failed to list cluster tasks")?;
 
-    let mut table = Table::new();
-    table.style = TableStyle::simple();
-    table.separate_rows = false;
+    output::print_list(format, table_opts, &tasks)
+}
 
-    table.add_row(Row::new(vec![
-        TableCell::new("id"),
-        TableCell::new(&task.id),
-    ]));
-    table.add_row(Row::new(vec![
-        TableCell::new("started_at"),
-        TableCell::new(&task.started_at.to_rfc3339()),
-    ]));
-    table.add_row(Row::new(vec![
-        TableCell::new("updated_at"),
-        TableCell::new(updated_at),
-    ]));
-    table.add_row(Row::new(vec![
-        TableCell::new("type"),
-        TableCell::new(&task.task_type),
-    ]));
-    table.add_row(Row::new(vec![
-        TableCell::new("status"),
-        TableCell::new(&task.status),
-    ]));
+impl output::Item for task::schemas::Task {
+    const KIND: &'static str = "task";
 
-    println!("{}", table.render());
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn fields() -> Vec<output::Field<Self>> {
+        vec![
+            output::Field::new("id", |task| task.id.clone()),
+            output::Field::new("started_at", |task| task.started_at.to_rfc3339()),
+            output::Field::new("updated_at", |task| {
+                task.updated_at
+                    .map(|time| time.to_rfc3339())
+                    .unwrap_or_default()
+            }),
+            output::Field::new("type", |task| task.task_type.to_string()),
+            output::Field::new("status", |task| task.status.to_string()),
+            output::Field::wide("cluster_id", |task| task.cluster_id.clone()),
+        ]
+    }
 }
 
-pub(crate) fn list(client: &Client, output: &str, cluster_id: &str) -> Result<()> {
-    let tasks = client
-        .list_tasks(cluster_id)
+/// Get identifiers of all cluster tasks, they're used to find tasks started by a request.
+pub(crate) fn ids(client: &Client, cluster_id: &str) -> Result<HashSet<String>> {
+    let tasks = logging::call("list_tasks", &format!("cluster_id={}", cluster_id), || {
+        client.list_tasks(cluster_id)
+    })
+    .context("Failed to list cluster tasks")?;
+
+    Ok(tasks.into_iter().map(|task| task.id).collect())
+}
+
+/// Poll cluster tasks until at least `count` tasks of the type that are not known are started.
+///
+/// Tasks of other types, e.g. started by autorepair or an upgrade, are not counted.
+pub(crate) fn wait_started(
+    client: &Client,
+    cluster_id: &str,
+    known: &HashSet<String>,
+    task_type: &task::schemas::Type,
+    count: usize,
+    wait_opts: &wait::WaitOpts,
+) -> Result<Vec<task::schemas::Task>> {
+    let task_type = task_type.to_string();
+    let what = format!("{} tasks of cluster {} to start", task_type, cluster_id);
+
+    wait::poll(wait_opts, &what, || {
+        let tasks = logging::call("list_tasks", &format!("cluster_id={}", cluster_id), || {
+            client.list_tasks(cluster_id)
+        })
         .context("Failed to list cluster tasks")?;
 
-    match output {
-        "table" => list_print_table(&tasks),
-        "json" => json::print_json(tasks)?,
-        _ => bail!("Unknown output format"),
+        let started: Vec<task::schemas::Task> = tasks
+            .into_iter()
+            .filter(|task| !known.contains(&task.id) && task.task_type.to_string() == task_type)
+            .collect();
+        if started.len() >= count {
+            Ok(Some(started))
+        } else {
+            Ok(None)
+        }
+    })
+}
+
+/// Count of poll intervals to look for started tasks when the command doesn't wait for them.
+const FIND_POLLS: u64 = 3;
+
+/// Find tasks started by a successful request.
+///
+/// With the --wait flag it fails if no task is started before the timeout. Otherwise tasks are
+/// only looked for during a few poll intervals and a warning is printed if none are found, since
+/// the request itself has succeeded and it must not be retried.
+pub(crate) fn find_started(
+    client: &Client,
+    cluster_id: &str,
+    known: &HashSet<String>,
+    task_type: &task::schemas::Type,
+    wait_args: &conf::WaitArgs,
+) -> Result<Vec<task::schemas::Task>> {
+    let timeout = if wait_args.wait {
+        wait_args.timeout
+    } else {
+        wait_args
+            .timeout
+            .min(wait_args.poll_interval.saturating_mul(FIND_POLLS))
+    };
+    let wait_opts = wait::WaitOpts::new(timeout, wait_args.poll_interval);
+
+    match wait_started(client, cluster_id, known, task_type, 1, &wait_opts) {
+        Ok(tasks) => Ok(tasks),
+        Err(err) if wait_args.wait && err.is::<wait::TimeoutError>() => {
+            Err(err).context("Request succeeded but no started tasks were found")
+        }
+        Err(err) => {
+            eprintln!(
+                "Warning: request succeeded but its tasks can't be found: {:#}",
+                err
+            );
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// Run a request that starts cluster tasks of the type and get the started tasks.
+///
+/// Tasks are found by comparing cluster tasks before and after the request, they're polled until
+/// DONE when the --wait flag is set.
+pub(crate) fn track<F>(
+    client: &Client,
+    cluster_id: &str,
+    task_type: &task::schemas::Type,
+    wait_args: &conf::WaitArgs,
+    request: F,
+) -> Result<Vec<task::schemas::Task>>
+where
+    F: FnOnce() -> Result<()>,
+{
+    let known = ids(client, cluster_id)?;
+    request()?;
+    let tasks = find_started(client, cluster_id, &known, task_type, wait_args)?;
+
+    if wait_args.wait {
+        let wait_opts = wait::WaitOpts::new(wait_args.timeout, wait_args.poll_interval);
+        tasks
+            .iter()
+            .map(|task| wait_done(client, cluster_id, &task.id, &wait_opts))
+            .collect()
+    } else {
+        Ok(tasks)
+    }
+}
+
+/// Print tasks started by a command.
+pub(crate) fn print_started(format: &output::Format, tasks: &[task::schemas::Task]) -> Result<()> {
+    if tasks.is_empty() {
+        eprintln!("No started tasks found");
+        return Ok(());
+    }
+
+    output::print_list(format, &output::TableOpts::default(), tasks)
+}
+
+/// Poll the task until it becomes DONE, printing every status transition to stderr.
+pub(crate) fn wait_done(
+    client: &Client,
+    cluster_id: &str,
+    task_id: &str,
+    wait_opts: &wait::WaitOpts,
+) -> Result<task::schemas::Task> {
+    let mut last_status = String::new();
+    let what = format!("task {} to become DONE", task_id);
+
+    wait::poll(wait_opts, &what, || {
+        let task = logging::call(
+            "get_task",
+            &format!("cluster_id={} task_id={}", cluster_id, task_id),
+            || client.get_task(cluster_id, task_id),
+        )
+        .context("Failed to get cluster task")?;
+
+        let status = task.status.to_string();
+        if status != last_status {
+            eprintln!("task {} ({}): {}", task_id, task.task_type, status);
+            last_status = status;
+        }
+
+        match task.status {
+            task::schemas::Status::Done => Ok(Some(task)),
+            task::schemas::Status::Error => bail!("Task {} is in ERROR state", task_id),
+            _ => Ok(None),
+        }
+    })
+}
+
+/// Poll the task or all cluster tasks until none of them is IN_PROGRESS.
+///
+/// The tasks table is redrawn in place on a terminal, otherwise every status change is printed
+/// on its own line. It fails if any of the watched tasks ends with the ERROR or UNKNOWN status.
+pub(crate) fn watch(
+    client: &Client,
+    cluster_id: &str,
+    task_id: Option<&str>,
+    wait_opts: &wait::WaitOpts,
+) -> Result<()> {
+    let redraw = io::stdout().is_terminal();
+    let mut drawn_lines = 0;
+    let mut statuses: HashMap<String, String> = HashMap::new();
+    let mut watched: HashSet<String> = HashSet::new();
+
+    let what = match task_id {
+        Some(task_id) => format!("task {} to finish", task_id),
+        None => format!("tasks of cluster {} to finish", cluster_id),
     };
 
+    let tasks = wait::poll(wait_opts, &what, || {
+        let tasks = match task_id {
+            Some(task_id) => vec![logging::call(
+                "get_task",
+                &format!("cluster_id={} task_id={}", cluster_id, task_id),
+                || client.get_task(cluster_id, task_id),
+            )
+            .context("Failed to get cluster task")?],
+            None => logging::call("list_tasks", &format!("cluster_id={}", cluster_id), || {
+                client.list_tasks(cluster_id)
+            })
+            .context("Failed to list cluster tasks")?,
+        };
+
+        // Only tasks that are in progress are watched when the whole cluster is watched.
+        for task in tasks.iter() {
+            if task_id.is_some() || in_progress(task) {
+                watched.insert(task.id.clone());
+            }
+        }
+
+        if redraw {
+            let table = watch_table(&tasks, &watched);
+            if drawn_lines > 0 {
+                print!("\x1b[{}A\x1b[J", drawn_lines);
+            }
+            print!("{}", table);
+            io::stdout().flush().context("Failed to print tasks")?;
+            drawn_lines = table.lines().count();
+        } else {
+            for task in tasks.iter().filter(|task| watched.contains(&task.id)) {
+                let status = task.status.to_string();
+                if statuses.get(&task.id) != Some(&status) {
+                    println!("task {} ({}): {}", task.id, task.task_type, status);
+                    statuses.insert(task.id.clone(), status);
+                }
+            }
+        }
+
+        if tasks.iter().any(in_progress) {
+            Ok(None)
+        } else {
+            Ok(Some(tasks))
+        }
+    })?;
+
+    // Nothing is watched when the whole cluster is watched and none of its tasks is in progress.
+    if watched.is_empty() {
+        eprintln!("No tasks in progress");
+        return Ok(());
+    }
+
+    let failed: Vec<String> = tasks
+        .iter()
+        .filter(|task| {
+            watched.contains(&task.id)
+                && matches!(
+                    task.status,
+                    task::schemas::Status::Error | task::schemas::Status::Unknown
+                )
+        })
+        .map(|task| format!("{} ({})", task.id, task.status))
+        .collect();
+    if !failed.is_empty() {
+        bail!("Task(s) {} didn't finish successfully", failed.join(", "));
+    }
+
     Ok(())
 }
 
-fn list_print_table(tasks: &[task::schemas::Task]) {
+fn in_progress(task: &task::schemas::Task) -> bool {
+    matches!(task.status, task::schemas::Status::InProgress)
+}
+
+fn watch_table(tasks: &[task::schemas::Task], watched: &HashSet<String>) -> String {
     let mut table = Table::new();
     table.style = TableStyle::simple();
 
     table.add_row(Row::new(vec![
         TableCell::new_with_alignment("id", 1, Alignment::Center),
-        TableCell::new_with_alignment("started_at", 1, Alignment::Center),
-        TableCell::new_with_alignment("updated_at", 1, Alignment::Center),
         TableCell::new_with_alignment("type", 1, Alignment::Center),
         TableCell::new_with_alignment("status", 1, Alignment::Center),
+        TableCell::new_with_alignment("started_at", 1, Alignment::Center),
+        TableCell::new_with_alignment("updated_at", 1, Alignment::Center),
     ]));
 
-    for task in tasks.iter() {
+    for task in tasks.iter().filter(|task| watched.contains(&task.id)) {
         let updated_at = match &task.updated_at {
             Some(time) => time.to_rfc3339(),
             None => String::new(),
@@ -89,12 +371,12 @@ fn list_print_table(tasks: &[task::schemas::Task]) {
 
         table.add_row(Row::new(vec![
             TableCell::new(&task.id),
-            TableCell::new(&task.started_at.to_rfc3339()),
-            TableCell::new(updated_at),
             TableCell::new(&task.task_type),
             TableCell::new(&task.status),
+            TableCell::new(task.started_at.to_rfc3339()),
+            TableCell::new(updated_at),
         ]));
     }
 
-    println!("{}", table.render());
+    format!("{}\n", table.render())
 }
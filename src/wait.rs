@@ -0,0 +1,87 @@
+use anyhow::Result;
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::conf;
+
+/// Options that control how long and how often a resource is polled.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct WaitOpts {
+    pub(crate) timeout: Duration,
+    pub(crate) poll_interval: Duration,
+}
+
+impl WaitOpts {
+    pub(crate) fn new(timeout_secs: u64, poll_interval_secs: u64) -> WaitOpts {
+        WaitOpts {
+            timeout: Duration::from_secs(timeout_secs),
+            poll_interval: Duration::from_secs(poll_interval_secs),
+        }
+    }
+
+    /// Get options of the --wait flag, there is nothing to wait for if it's not set.
+    pub(crate) fn from_args(wait_args: &conf::WaitArgs) -> Option<WaitOpts> {
+        if wait_args.wait {
+            Some(WaitOpts::new(wait_args.timeout, wait_args.poll_interval))
+        } else {
+            None
+        }
+    }
+}
+
+/// Error of a poll that reached its timeout.
+#[derive(Debug)]
+pub(crate) struct TimeoutError {
+    timeout: Duration,
+    what: String,
+}
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Timed out after {} seconds waiting for {}",
+            self.timeout.as_secs(),
+            self.what
+        )
+    }
+}
+
+impl std::error::Error for TimeoutError {}
+
+/// Check that the poll interval is at least one second, so the API is not polled in a busy loop.
+pub(crate) fn check_poll_interval(value: String) -> Result<(), String> {
+    match value.parse::<u64>() {
+        Ok(interval) if interval >= 1 => Ok(()),
+        _ => Err(format!(
+            "invalid poll interval \"{}\", expected at least 1 second",
+            value
+        )),
+    }
+}
+
+/// Call `check` every poll interval until it returns a value or the timeout is reached.
+pub(crate) fn poll<T, F>(opts: &WaitOpts, what: &str, mut check: F) -> Result<T>
+where
+    F: FnMut() -> Result<Option<T>>,
+{
+    let started = Instant::now();
+
+    loop {
+        if let Some(value) = check()? {
+            return Ok(value);
+        }
+
+        let elapsed = started.elapsed();
+        if elapsed >= opts.timeout {
+            return Err(TimeoutError {
+                timeout: opts.timeout,
+                what: String::from(what),
+            }
+            .into());
+        }
+
+        thread::sleep(opts.poll_interval.min(opts.timeout - elapsed));
+    }
+}
@@ -0,0 +1,10 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+pub(crate) fn print_yaml<T: Serialize>(data: T) -> Result<()> {
+    let serialized = serde_yaml::to_string(&data).context("Failed to serialize YAML")?;
+
+    print!("{}", serialized);
+
+    Ok(())
+}